@@ -1,3 +1,4 @@
+use std::env;
 use std::ffi::OsStr;
 use std::io::ErrorKind;
 use std::process::{Command, Output};
@@ -24,6 +25,13 @@ pub struct BetterCommand {
     command: Command,
 }
 
+/// Whether an executable can be found in the `PATH`, without running it
+pub fn is_installed(executable: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|path| path.join(executable).is_file()))
+        .unwrap_or(false)
+}
+
 fn u8vec_to_string(s: Vec<u8>) -> String {
     String::from_utf8_lossy(&s).to_string()
 }
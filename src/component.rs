@@ -1,10 +1,19 @@
 use crate::config::global_config::GlobalConfig;
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Section};
+use crate::severity::{Problem, Severity};
 use async_trait::async_trait;
+use serde::Serialize;
+use std::fmt::Display;
 
 /// Boxed component with all other traits
-// Send is required because print is async
+// Send is required because components gather their data concurrently on other threads
 pub type BoxedComponent = Box<dyn Component + Send>;
 
+/// Boxed output of a component
+// Send is required to return the output from the thread that gathered it
+pub type BoxedOutput = Box<dyn ComponentOutput + Send>;
+
 /// Return type for the prepare phase
 pub type PrepareReturn = (BoxedComponent, Option<Constraints>);
 
@@ -30,8 +39,101 @@ pub trait Component {
     /// Otherwise, simply return `self` if there is no data to save from the preparation phase
     fn prepare(self: Box<Self>, _global_config: &GlobalConfig) -> PrepareReturn;
 
-    /// Print the component to stdout
-    async fn print(self: Box<Self>, global_config: &GlobalConfig, width: Option<usize>);
+    /// Gather the data shown by the component
+    /// Components gather their data concurrently, so they must not print anything themselves
+    /// If gathering fails, return an `ErrorOutput` describing why
+    async fn get_output(self: Box<Self>, global_config: &GlobalConfig) -> BoxedOutput;
+}
+
+/// Serialize the data gathered by a component as JSON
+/// This is implemented for everything that implements `Serialize`,
+/// so outputs only need to derive `Serialize`
+// `Serialize` itself cannot be used in a trait object
+pub trait ToJson {
+    fn to_json(&self) -> serde_json::Result<serde_json::Value>;
+}
+
+impl<T: Serialize> ToJson for T {
+    fn to_json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(self)
+    }
+}
+
+/// This trait should be implemented for the data gathered by each component
+/// It turns the data into a `Section`, which is then rendered into the final output
+pub trait ComponentOutput: ToJson {
+    /// Lay out the data as a section of the motd
+    /// `width` is the width of the widest component, as determined in the prepare phase
+    fn to_section(&self, global_config: &GlobalConfig, width: Option<usize>) -> Section;
+
+    /// Metrics for the Prometheus output format
+    /// Components without anything worth monitoring can keep the default of no metrics
+    fn metrics(&self) -> Vec<Metric> {
+        vec![]
+    }
+
+    /// Problems worth alerting on, for the exit code and the summary
+    fn problems(&self) -> Vec<Problem> {
+        vec![]
+    }
+
+    /// Whether this is the output of a component that failed to gather its data
+    fn is_error(&self) -> bool {
+        false
+    }
+
+    /// The worst severity of all problems
+    fn severity(&self) -> Severity {
+        self.problems()
+            .iter()
+            .map(|problem| problem.severity)
+            .max()
+            .unwrap_or(Severity::Ok)
+    }
+}
+
+/// Output of a component that failed to gather its data
+#[derive(Serialize)]
+pub struct ErrorOutput {
+    /// What failed, for example "Banner error", which the summary already says by its name
+    #[serde(skip)]
+    pub context: String,
+    pub error: String,
+}
+
+impl ComponentOutput for ErrorOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(None);
+        section.push(vec![Element::plain(format!(
+            "{}: {}",
+            self.context, self.error
+        ))]);
+        section
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        vec![Problem::new(Severity::Unknown, &self.error)]
+    }
+
+    fn is_error(&self) -> bool {
+        true
+    }
+}
+
+/// Box the output of a component, or an `ErrorOutput` if gathering the data failed
+/// `context` is shown before the error message in the motd, for example "Banner error"
+pub fn output_or_error<T, E>(result: Result<T, E>, context: &str) -> BoxedOutput
+where
+    T: ComponentOutput + Send + 'static,
+    E: Display,
+{
+    match result {
+        Ok(output) => Box::new(output),
+        Err(err) => Box::new(ErrorOutput {
+            context: context.to_string(),
+            error: err.to_string(),
+        }),
+    }
 }
 
 /// Implement the default prepare method which returns the component unmodified and no constraints
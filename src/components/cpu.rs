@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::Duration;
+use systemstat::{CPULoad, Platform, System};
+
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
+use crate::config::global_config::GlobalConfig;
+use crate::constants::INDENT_WIDTH;
+use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Section};
+use crate::severity::{Problem, Severity, UsageThresholds};
+
+/// How long the CPU time is sampled to compute the usage
+const SAMPLE_DURATION: Duration = Duration::from_millis(500);
+
+/// Load averages as percentages of the number of cores
+const DEFAULT_LOAD_THRESHOLDS: UsageThresholds = UsageThresholds::new(100, 200);
+
+#[derive(Debug, Deserialize)]
+pub struct Cpu {
+    /// Show a usage bar for every core instead of one for all of them
+    #[serde(default)]
+    per_core: bool,
+    /// Thresholds for the usage bars
+    #[serde(flatten)]
+    thresholds: UsageThresholds,
+    /// Thresholds for the load averages, as percentages of the number of cores
+    #[serde(default)]
+    load_thresholds: UsageThresholds,
+}
+
+#[async_trait]
+impl Component for Cpu {
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "CPU error")
+    }
+    default_prepare!();
+}
+
+#[derive(Serialize)]
+struct LoadAverage {
+    one: f32,
+    five: f32,
+    fifteen: f32,
+}
+
+#[derive(Serialize)]
+pub struct CpuOutput {
+    #[serde(skip)]
+    thresholds: UsageThresholds,
+    #[serde(skip)]
+    load_thresholds: UsageThresholds,
+    cores: usize,
+    load_average: LoadAverage,
+    /// Fraction of the time all cores were busy while sampling
+    usage_ratio: f64,
+    /// Fraction of the time each core was busy, if shown per core
+    core_usage_ratios: Vec<f64>,
+}
+
+impl CpuOutput {
+    /// Severity of a load average, relative to the number of cores
+    fn load_severity(&self, load: f32) -> Severity {
+        self.load_thresholds
+            .severity(load as f64 / self.cores as f64)
+    }
+
+    fn cores_label(&self) -> String {
+        match self.cores {
+            1 => "1 core".to_string(),
+            cores => format!("{} cores", cores),
+        }
+    }
+}
+
+fn busy_ratio(load: &CPULoad) -> f64 {
+    (load.user + load.nice + load.system + load.interrupt) as f64
+}
+
+fn format_percent(ratio: f64) -> String {
+    format!("{:.0}%", ratio * 100.0)
+}
+
+impl ComponentOutput for CpuOutput {
+    fn to_section(&self, global_config: &GlobalConfig, width: Option<usize>) -> Section {
+        let width = width.unwrap_or(global_config.progress_width - INDENT_WIDTH);
+        let mut section = Section::new(Some("CPU"));
+
+        let load = &self.load_average;
+        let mut line = vec![Element::plain(format!(
+            "{}Load (1m, 5m, 15m): ",
+            " ".repeat(INDENT_WIDTH)
+        ))];
+        for (i, value) in [load.one, load.five, load.fifteen].into_iter().enumerate() {
+            if i > 0 {
+                line.push(Element::plain(", "));
+            }
+            line.push(Element::colored(
+                format!("{:.2}", value),
+                self.load_severity(value).bar_color(),
+            ));
+        }
+        line.push(Element::plain(format!(" ({})", self.cores_label())));
+        section.push(line);
+
+        if self.core_usage_ratios.is_empty() {
+            section.push(vec![Element::plain(format!(
+                "{}Usage: {}",
+                " ".repeat(INDENT_WIDTH),
+                format_percent(self.usage_ratio)
+            ))]);
+            section.push(vec![
+                Element::plain(" ".repeat(INDENT_WIDTH)),
+                Element::bar(
+                    width,
+                    self.usage_ratio,
+                    self.thresholds.severity(self.usage_ratio).bar_color(),
+                ),
+            ]);
+        } else {
+            // Labels and percentages are padded so the bars of all cores line up
+            let label_width = format!("{}", self.cores - 1).len();
+            for (core, ratio) in self.core_usage_ratios.iter().enumerate() {
+                let label = format!("{:>label_width$} ", core, label_width = label_width);
+                let percent = format!(" {:>4}", format_percent(*ratio));
+                section.push(vec![
+                    Element::plain(format!("{}{}", " ".repeat(INDENT_WIDTH), label)),
+                    Element::bar(
+                        width.saturating_sub(label.len() + percent.len()),
+                        *ratio,
+                        self.thresholds.severity(*ratio).bar_color(),
+                    ),
+                    Element::plain(percent),
+                ]);
+            }
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let load = &self.load_average;
+        let mut metrics = vec![
+            Metric::new("motd_cpu_cores", "Number of CPU cores", self.cores as f64),
+            Metric::new(
+                "motd_cpu_usage_ratio",
+                "Fraction of the time the CPU cores were busy",
+                self.usage_ratio,
+            ),
+        ];
+        for (period, value) in [("1m", load.one), ("5m", load.five), ("15m", load.fifteen)] {
+            metrics.push(
+                Metric::new("motd_load_average", "Load average", value).label("period", period),
+            );
+        }
+        for (core, ratio) in self.core_usage_ratios.iter().enumerate() {
+            metrics.push(
+                Metric::new(
+                    "motd_cpu_core_usage_ratio",
+                    "Fraction of the time a CPU core was busy",
+                    *ratio,
+                )
+                .label("core", core.to_string()),
+            );
+        }
+        metrics
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        // The 5 minute average is used so short spikes are not reported,
+        // the usage is sampled too briefly and alongside the other components
+        let load = self.load_average.five;
+        let severity = self.load_severity(load);
+        if severity != Severity::Ok {
+            problems.push(Problem::new(
+                severity,
+                format!(
+                    "5 minute load average is {:.2} with {}",
+                    load,
+                    self.cores_label()
+                ),
+            ));
+        }
+
+        problems
+    }
+}
+
+impl Cpu {
+    pub fn get_output_or_error(self) -> Result<CpuOutput, std::io::Error> {
+        let sys = System::new();
+
+        let measurement = sys.cpu_load()?;
+        thread::sleep(SAMPLE_DURATION);
+        let core_loads = measurement.done()?;
+        let cores = core_loads.len().max(1);
+
+        let core_usage_ratios: Vec<f64> = core_loads.iter().map(busy_ratio).collect();
+        let usage_ratio = core_usage_ratios.iter().sum::<f64>() / cores as f64;
+
+        let load_average = sys.load_average()?;
+
+        Ok(CpuOutput {
+            thresholds: self.thresholds,
+            load_thresholds: self.load_thresholds.or(DEFAULT_LOAD_THRESHOLDS),
+            cores,
+            load_average: LoadAverage {
+                one: load_average.one,
+                five: load_average.five,
+                fifteen: load_average.fifteen,
+            },
+            usage_ratio,
+            core_usage_ratios: match self.per_core {
+                true => core_usage_ratios,
+                false => vec![],
+            },
+        })
+    }
+}
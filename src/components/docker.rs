@@ -1,49 +1,707 @@
 use crate::constants::INDENT_WIDTH;
 use async_trait::async_trait;
-use docker_api::models::ContainerSummary;
+use docker_api::models::{ContainerSummary, Port};
 use docker_api::opts::ContainerListOpts;
-use docker_api::{Docker as DockerAPI, Result as DockerResult};
+use docker_api::Docker as DockerAPI;
+use futures_util::future::join_all;
+use futures_util::StreamExt;
+use glob::Pattern;
+use itertools::Itertools;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
-use termion::{color, style};
+use systemstat::ByteSize;
 
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::default_prepare;
+use crate::docker::Endpoint;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+use crate::severity::{Problem, Severity, UsageThresholds};
 
+#[derive(Debug, Deserialize)]
 pub struct Docker {
-    pub containers: HashMap<String, String>,
+    /// Show the result of the `HEALTHCHECK` of containers that have one
+    #[serde(default = "default_true")]
+    show_health: bool,
+    /// Show how often containers were restarted, if they were
+    #[serde(default = "default_true")]
+    show_restarts: bool,
+    #[serde(default)]
+    show_image: bool,
+    /// Show the ports that are published on the host
+    #[serde(default)]
+    show_ports: bool,
+    /// Glob patterns of names of containers to show, without the leading slash
+    #[serde(default)]
+    names: Vec<String>,
+    /// Regular expression of names of containers to show, without the leading slash
+    name_regex: Option<String>,
+    /// Show containers with one of these labels, as `key` or `key=value`
+    #[serde(default)]
+    labels: Vec<String>,
+    /// Glob patterns of Compose projects whose containers to show
+    #[serde(default)]
+    projects: Vec<String>,
+    /// Show the containers of each Compose project below it, with how many of them are running
+    #[serde(default = "default_true")]
+    group_projects: bool,
+    /// Show the CPU and memory usage of running containers
+    #[serde(default)]
+    show_stats: bool,
+    #[serde(default)]
+    stats_thresholds: UsageThresholds,
+    /// Show the disk space used by images, volumes and the build cache, like `docker system df`
+    #[serde(default)]
+    show_disk_usage: bool,
+    #[serde(flatten)]
+    endpoint: Endpoint,
+    // Must come after the other fields, since it takes all remaining keys
+    #[serde(flatten)]
+    containers: HashMap<String, String>,
 }
 
+fn default_true() -> bool {
+    true
+}
+
+const PROJECT_LABEL: &str = "com.docker.compose.project";
+const SERVICE_LABEL: &str = "com.docker.compose.service";
+
 #[async_trait]
 impl Component for Docker {
-    async fn print(self: Box<Self>, _global_config: &GlobalConfig, _width: Option<usize>) {
-        println!("Docker:");
-        self.print_or_error()
-            .await
-            .unwrap_or_else(|err| println!("Docker status error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error().await, "Docker status error")
     }
     default_prepare!();
 }
 
-#[cfg(unix)]
-pub fn new_docker() -> DockerResult<DockerAPI> {
-    Ok(DockerAPI::unix("/var/run/docker.sock"))
+struct Container {
+    summary: ContainerSummary,
+    name: String,
 }
 
-#[cfg(not(unix))]
-pub fn new_docker() -> DockerResult<DockerAPI> {
-    DockerAPI::new("tcp://127.0.0.1:8080")
+/// The patterns of the configuration, compiled once for all containers
+struct Selection {
+    names: Vec<Pattern>,
+    name_regex: Option<Regex>,
+    labels: Vec<(String, Option<String>)>,
+    projects: Vec<Pattern>,
 }
 
-struct Container {
-    summary: ContainerSummary,
+impl Selection {
+    fn matches(&self, summary: &ContainerSummary) -> bool {
+        let names = summary.names.iter().flatten();
+        let labels = summary.labels.as_ref();
+        let label = |key: &str| labels.and_then(|labels| labels.get(key));
+
+        names.clone().any(|name| {
+            let name = name.trim_start_matches('/');
+            self.names.iter().any(|pattern| pattern.matches(name))
+                || self
+                    .name_regex
+                    .as_ref()
+                    .is_some_and(|regex| regex.is_match(name))
+        }) || self.labels.iter().any(|(key, value)| match value {
+            Some(value) => label(key) == Some(value),
+            None => label(key).is_some(),
+        }) || label(PROJECT_LABEL)
+            .is_some_and(|project| self.projects.iter().any(|pattern| pattern.matches(project)))
+    }
+}
+
+/// State of one configured container
+#[derive(Serialize)]
+pub struct ContainerStatus {
     name: String,
+    /// Compose project the container belongs to
+    project: Option<String>,
+    state: Option<String>,
+    /// How long the container is up or since when it exited
+    status: Option<String>,
+    /// `healthy`, `unhealthy` or `starting` for containers with a `HEALTHCHECK`
+    health: Option<String>,
+    restart_count: Option<u64>,
+    image: Option<String>,
+    /// Published ports, for example `8080->80/tcp`
+    ports: Vec<String>,
+    stats: Option<ContainerStats>,
+}
+
+/// Resource usage of a running container
+#[derive(Serialize)]
+struct ContainerStats {
+    /// Fraction of the CPU time of all cores of the host
+    cpu_usage_ratio: f64,
+    /// Without the page cache that can be reclaimed, like `docker stats`
+    memory_used: u64,
+    /// Memory limit of the container, or the memory of the host without one
+    memory_limit: u64,
+}
+
+impl ContainerStats {
+    fn memory_ratio(&self) -> f64 {
+        match self.memory_limit {
+            0 => 0.0,
+            limit => self.memory_used as f64 / limit as f64,
+        }
+    }
+
+    /// CPU and memory usage bars side by side
+    fn line(&self, thresholds: &UsageThresholds, indent: usize, width: usize) -> Vec<Element> {
+        let cpu = format!(" {:>4} ", format_percent(self.cpu_usage_ratio));
+        let memory = format!(" {:>9}", ByteSize::b(self.memory_used).to_string());
+        let spacing = " ".repeat(2);
+        let bar_width =
+            width.saturating_sub(2 * "CPU ".len() + cpu.len() + spacing.len() + memory.len()) / 2;
+
+        vec![
+            Element::plain(format!("{}CPU ", " ".repeat(indent))),
+            Element::bar(
+                bar_width,
+                self.cpu_usage_ratio,
+                thresholds.severity(self.cpu_usage_ratio).bar_color(),
+            ),
+            Element::plain(format!("{}{}Mem ", cpu, spacing)),
+            Element::bar(
+                bar_width,
+                self.memory_ratio(),
+                thresholds.severity(self.memory_ratio()).bar_color(),
+            ),
+            Element::plain(memory),
+        ]
+    }
+}
+
+fn format_percent(ratio: f64) -> String {
+    format!("{:.0}%", ratio * 100.0)
+}
+
+/// Space used by one kind of Docker objects
+#[derive(Serialize)]
+struct Usage {
+    count: usize,
+    size: u64,
+    /// Size of the objects that are not used, which `docker system prune` would remove
+    reclaimable: u64,
+}
+
+#[derive(Serialize)]
+struct DiskUsage {
+    images: Usage,
+    /// Images without a tag, usually replaced by newer versions
+    dangling_images: usize,
+    volumes: Usage,
+    build_cache: Usage,
+}
+
+impl DiskUsage {
+    fn usages(&self) -> [(&str, &Usage); 3] {
+        [
+            ("images", &self.images),
+            ("volumes", &self.volumes),
+            ("build_cache", &self.build_cache),
+        ]
+    }
+}
+
+impl ContainerStatus {
+    fn severity(&self) -> Severity {
+        match (self.state.as_deref(), self.health.as_deref()) {
+            (Some("dead"), _) | (_, Some("unhealthy")) => Severity::Critical,
+            (
+                Some("created") | Some("restarting") | Some("paused") | Some("removing")
+                | Some("configured"),
+                _,
+            ) => Severity::Warning,
+            _ => Severity::Ok,
+        }
+    }
+
+    /// Name including the Compose project, to tell apart services of different projects
+    fn qualified_name(&self, group_projects: bool) -> String {
+        match (group_projects, &self.project) {
+            (true, Some(project)) => format!("{}/{}", project, self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// Name, state and the enabled details in one line
+    fn line(&self, indent: usize, padding: usize) -> Vec<Element> {
+        let status_color = match self.state.as_deref() {
+            Some("created") | Some("restarting") | Some("paused") | Some("removing")
+            | Some("configured") => Color::Yellow,
+            Some("running") => Color::Green,
+            Some("exited") => Color::LightBlack,
+            Some("dead") => Color::Red,
+            _ => Color::White,
+        };
+        let status = self.status.as_deref().unwrap_or("?");
+        let mut line = vec![
+            Element::plain(format!(
+                "{indent}{name}: {padding}",
+                indent = " ".repeat(indent),
+                name = self.name,
+                padding = " ".repeat(padding - self.name.len()),
+            )),
+            Element::colored(
+                match self.health {
+                    Some(_) => strip_health(status),
+                    None => status,
+                },
+                status_color,
+            ),
+        ];
+
+        if let Some(health) = &self.health {
+            let health_color = match health.as_str() {
+                "healthy" => Color::Green,
+                "unhealthy" => Color::Red,
+                _ => Color::Yellow,
+            };
+            line.push(Element::plain(", "));
+            line.push(Element::colored(health, health_color));
+        }
+        if let Some(restart_count @ 1..) = self.restart_count {
+            line.push(Element::plain(", "));
+            line.push(Element::colored(
+                format!(
+                    "{} restart{}",
+                    restart_count,
+                    if restart_count == 1 { "" } else { "s" }
+                ),
+                Color::Yellow,
+            ));
+        }
+        if let Some(image) = &self.image {
+            line.push(Element::plain(format!(", {}", image)));
+        }
+        if !self.ports.is_empty() {
+            line.push(Element::plain(format!(", {}", self.ports.join(" "))));
+        }
+
+        line
+    }
+}
+
+/// Docker appends the health to the status, e.g. `Up 3 hours (healthy)`,
+/// which is left out when it is shown separately
+fn strip_health(status: &str) -> &str {
+    [" (healthy)", " (unhealthy)", " (health: starting)"]
+        .iter()
+        .find_map(|suffix| status.strip_suffix(suffix))
+        .unwrap_or(status)
+}
+
+/// Published ports without duplicates for IPv4 and IPv6, and without the address if it is any address
+fn format_ports(ports: &[Port]) -> Vec<String> {
+    ports
+        .iter()
+        .filter_map(|port| {
+            let public_port = port.public_port?;
+            Some(match port.ip.as_deref() {
+                None | Some("") | Some("0.0.0.0") | Some("::") => {
+                    format!("{}->{}/{}", public_port, port.private_port, port.type_)
+                }
+                Some(ip) if ip.contains(':') => format!(
+                    "[{}]:{}->{}/{}",
+                    ip, public_port, port.private_port, port.type_
+                ),
+                Some(ip) => format!(
+                    "{}:{}->{}/{}",
+                    ip, public_port, port.private_port, port.type_
+                ),
+            })
+        })
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// One line per container, with the names (first column) padded to the same length,
+/// and its resource usage below it if known
+fn lines(
+    containers: &[&ContainerStatus],
+    thresholds: &UsageThresholds,
+    indent: usize,
+    width: usize,
+) -> Vec<Vec<Element>> {
+    let padding = containers
+        .iter()
+        .map(|container| container.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![];
+    for container in containers {
+        lines.push(container.line(indent, padding));
+        if let Some(stats) = &container.stats {
+            // Aligned with the status, but not further than half of the width
+            let stats_indent = (indent + padding + 2).min(INDENT_WIDTH + width / 2);
+            lines.push(stats.line(
+                thresholds,
+                stats_indent,
+                (width + INDENT_WIDTH).saturating_sub(stats_indent),
+            ));
+        }
+    }
+    lines
+}
+
+#[derive(Serialize)]
+pub struct DockerOutput {
+    #[serde(skip)]
+    group_projects: bool,
+    #[serde(skip)]
+    stats_thresholds: UsageThresholds,
+    containers: Vec<ContainerStatus>,
+    /// Configured containers that Docker does not know about
+    missing: Vec<String>,
+    disk_usage: Option<DiskUsage>,
+}
+
+impl ComponentOutput for DockerOutput {
+    fn to_section(&self, global_config: &GlobalConfig, width: Option<usize>) -> Section {
+        let width = width.unwrap_or(global_config.progress_width - INDENT_WIDTH);
+        let mut section = Section::new(Some("Docker"));
+
+        for docker_name in &self.missing {
+            section.push(vec![
+                Element::plain(" ".repeat(INDENT_WIDTH)),
+                Element::colored(
+                    format!("Warning: Could not find Docker container `{}'", docker_name),
+                    Color::Yellow,
+                ),
+            ]);
+        }
+
+        // Containers of Compose projects are shown below their project
+        let (grouped, ungrouped): (Vec<&ContainerStatus>, Vec<&ContainerStatus>) = self
+            .containers
+            .iter()
+            .partition(|container| self.group_projects && container.project.is_some());
+
+        for line in lines(&ungrouped, &self.stats_thresholds, INDENT_WIDTH, width) {
+            section.push(line);
+        }
+
+        let projects = grouped
+            .into_iter()
+            .into_group_map_by(|container| container.project.as_deref().unwrap_or_default());
+        for (project, mut containers) in projects.into_iter().sorted_by_key(|(project, _)| *project)
+        {
+            let running = containers
+                .iter()
+                .filter(|container| container.state.as_deref() == Some("running"))
+                .count();
+            section.push(vec![
+                Element::plain(format!("{}{}: ", " ".repeat(INDENT_WIDTH), project)),
+                Element::colored(
+                    format!("{}/{} running", running, containers.len()),
+                    match running == containers.len() {
+                        true => Color::Green,
+                        false => Color::Yellow,
+                    },
+                ),
+            ]);
+            containers.sort_by(|a, b| a.name.cmp(&b.name));
+            for line in lines(&containers, &self.stats_thresholds, 2 * INDENT_WIDTH, width) {
+                section.push(line);
+            }
+        }
+
+        if let Some(disk_usage) = &self.disk_usage {
+            section.push(vec![Element::plain(format!(
+                "{}Disk usage:",
+                " ".repeat(INDENT_WIDTH)
+            ))]);
+            for (label, usage) in [
+                ("Images:", &disk_usage.images),
+                ("Volumes:", &disk_usage.volumes),
+                ("Build cache:", &disk_usage.build_cache),
+            ] {
+                let mut line = vec![Element::plain(format!(
+                    "{}{:<12} {}, {} reclaimable",
+                    " ".repeat(2 * INDENT_WIDTH),
+                    label,
+                    ByteSize::b(usage.size),
+                    ByteSize::b(usage.reclaimable),
+                ))];
+                if label == "Images:" && disk_usage.dangling_images > 0 {
+                    line.push(Element::plain(", "));
+                    line.push(Element::colored(
+                        format!("{} dangling", disk_usage.dangling_images),
+                        Color::Yellow,
+                    ));
+                }
+                section.push(line);
+            }
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let mut metrics = vec![];
+        for container in &self.containers {
+            metrics.push(
+                Metric::new(
+                    "motd_container_running",
+                    "Whether the Docker container is running",
+                    container.state.as_deref() == Some("running"),
+                )
+                .label("name", &container.name)
+                .label("project", container.project.as_deref().unwrap_or_default()),
+            );
+            if let Some(health) = &container.health {
+                metrics.push(
+                    Metric::new(
+                        "motd_container_healthy",
+                        "Whether the health check of the Docker container passes",
+                        health == "healthy",
+                    )
+                    .label("name", &container.name)
+                    .label("project", container.project.as_deref().unwrap_or_default()),
+                );
+            }
+            if let Some(restart_count) = container.restart_count {
+                metrics.push(
+                    Metric::new(
+                        "motd_container_restarts",
+                        "How often the Docker container was restarted",
+                        restart_count as f64,
+                    )
+                    .label("name", &container.name)
+                    .label("project", container.project.as_deref().unwrap_or_default()),
+                );
+            }
+            if let Some(stats) = &container.stats {
+                for metric in [
+                    Metric::new(
+                        "motd_container_cpu_usage_ratio",
+                        "Fraction of the CPU time of the host used by the Docker container",
+                        stats.cpu_usage_ratio,
+                    ),
+                    Metric::new(
+                        "motd_container_memory_used_bytes",
+                        "Memory used by the Docker container",
+                        stats.memory_used as f64,
+                    ),
+                    Metric::new(
+                        "motd_container_memory_limit_bytes",
+                        "Memory limit of the Docker container",
+                        stats.memory_limit as f64,
+                    ),
+                ] {
+                    metrics.push(
+                        metric
+                            .label("name", &container.name)
+                            .label("project", container.project.as_deref().unwrap_or_default()),
+                    );
+                }
+            }
+        }
+        if let Some(disk_usage) = &self.disk_usage {
+            for (kind, usage) in disk_usage.usages() {
+                metrics.push(
+                    Metric::new(
+                        "motd_docker_disk_usage_bytes",
+                        "Disk space used by Docker",
+                        usage.size as f64,
+                    )
+                    .label("type", kind),
+                );
+            }
+            for (kind, usage) in disk_usage.usages() {
+                metrics.push(
+                    Metric::new(
+                        "motd_docker_disk_reclaimable_bytes",
+                        "Disk space used by Docker for objects that are not in use",
+                        usage.reclaimable as f64,
+                    )
+                    .label("type", kind),
+                );
+            }
+        }
+        metrics
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        self.missing
+            .iter()
+            .map(|docker_name| {
+                Problem::new(
+                    Severity::Warning,
+                    format!("could not find container `{}'", docker_name),
+                )
+            })
+            .chain(
+                self.containers
+                    .iter()
+                    .filter(|container| container.severity() != Severity::Ok)
+                    .map(|container| {
+                        Problem::new(
+                            container.severity(),
+                            format!(
+                                "{} is {}",
+                                container.qualified_name(self.group_projects),
+                                match container.health.as_deref() {
+                                    Some("unhealthy") => "unhealthy",
+                                    _ => container.state.as_deref().unwrap_or("?"),
+                                }
+                            ),
+                        )
+                    }),
+            )
+            .chain(self.containers.iter().filter_map(|container| {
+                let stats = container.stats.as_ref()?;
+                let severity = self.stats_thresholds.severity(stats.memory_ratio());
+                (severity != Severity::Ok).then(|| {
+                    Problem::new(
+                        severity,
+                        format!(
+                            "{} uses {} of its memory",
+                            container.qualified_name(self.group_projects),
+                            format_percent(stats.memory_ratio())
+                        ),
+                    )
+                })
+            }))
+            .collect()
+    }
+}
+
+/// Resource usage from the stats of a running container
+fn parse_stats(sample: &Value) -> Option<ContainerStats> {
+    let (cpu, precpu) = (&sample["cpu_stats"], &sample["precpu_stats"]);
+    let cpu_delta = cpu["cpu_usage"]["total_usage"]
+        .as_u64()?
+        .saturating_sub(precpu["cpu_usage"]["total_usage"].as_u64().unwrap_or(0));
+    let system_delta = cpu["system_cpu_usage"]
+        .as_u64()?
+        .saturating_sub(precpu["system_cpu_usage"].as_u64().unwrap_or(0));
+
+    let memory = &sample["memory_stats"];
+    // cgroup v2 and v1 respectively
+    let inactive_file = ["inactive_file", "total_inactive_file"]
+        .iter()
+        .find_map(|key| memory["stats"][key].as_u64())
+        .unwrap_or(0);
+
+    Some(ContainerStats {
+        cpu_usage_ratio: match system_delta {
+            0 => 0.0,
+            _ => cpu_delta as f64 / system_delta as f64,
+        },
+        memory_used: memory["usage"].as_u64()?.saturating_sub(inactive_file),
+        memory_limit: memory["limit"].as_u64()?,
+    })
+}
+
+async fn get_stats(docker: &DockerAPI, id: &str) -> Option<ContainerStats> {
+    let container = docker.containers().get(id);
+    let mut samples = container.stats().take(2);
+    let mut last = None;
+    // The first sample usually has no previous CPU usage to compare with,
+    // the next one comes about a second later
+    while let Some(Ok(sample)) = samples.next().await {
+        let has_previous = sample["precpu_stats"]["system_cpu_usage"]
+            .as_u64()
+            .is_some_and(|usage| usage > 0);
+        last = Some(sample);
+        if has_previous {
+            break;
+        }
+    }
+    parse_stats(&last?)
+}
+
+async fn get_disk_usage(docker: &DockerAPI) -> docker_api::Result<DiskUsage> {
+    let data_usage = docker.data_usage().await?;
+    let images = data_usage.images.unwrap_or_default();
+    let volumes = data_usage.volumes.unwrap_or_default();
+    let build_cache = data_usage.build_cache.unwrap_or_default();
+
+    // Sizes that are not known are -1
+    let size = |size: i64| size.max(0) as u64;
+    let volume_usage = |volume: &docker_api::models::Volume| {
+        volume
+            .usage_data
+            .as_ref()
+            .map(|usage_data| (size(usage_data.size as i64), usage_data.ref_count))
+    };
+
+    Ok(DiskUsage {
+        images: Usage {
+            count: images.len(),
+            // Layers shared by images only take space once
+            size: size(data_usage.layers_size.unwrap_or(0)),
+            reclaimable: images
+                .iter()
+                .filter(|image| image.containers == 0)
+                .map(|image| size(image.size - image.shared_size.max(0) as i64))
+                .sum(),
+        },
+        dangling_images: images
+            .iter()
+            .filter(|image| image.repo_tags.iter().all(|tag| tag == "<none>:<none>"))
+            .count(),
+        volumes: Usage {
+            count: volumes.len(),
+            size: volumes
+                .iter()
+                .filter_map(volume_usage)
+                .map(|(size, _)| size)
+                .sum(),
+            reclaimable: volumes
+                .iter()
+                .filter_map(volume_usage)
+                .filter(|(_, ref_count)| *ref_count == 0)
+                .map(|(size, _)| size)
+                .sum(),
+        },
+        build_cache: Usage {
+            count: build_cache.len(),
+            size: build_cache
+                .iter()
+                .map(|cache| size(cache.size.unwrap_or(0) as i64))
+                .sum(),
+            reclaimable: build_cache
+                .iter()
+                .filter(|cache| !cache.in_use.unwrap_or(false) && !cache.shared.unwrap_or(false))
+                .map(|cache| size(cache.size.unwrap_or(0) as i64))
+                .sum(),
+        },
+    })
 }
 
 impl Docker {
-    pub async fn print_or_error(mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let docker = new_docker()?;
+    pub async fn get_output_or_error(mut self) -> Result<DockerOutput, Box<dyn std::error::Error>> {
+        let connection = self.endpoint.connect().await?;
+        let docker = &connection.docker;
+
+        let selection = Selection {
+            names: self
+                .names
+                .iter()
+                .map(|pattern| Pattern::new(pattern))
+                .collect::<Result<_, _>>()?,
+            name_regex: self.name_regex.as_deref().map(Regex::new).transpose()?,
+            labels: self
+                .labels
+                .iter()
+                .map(|label| match label.split_once('=') {
+                    Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                    None => (label.to_string(), None),
+                })
+                .collect(),
+            projects: self
+                .projects
+                .iter()
+                .map(|pattern| Pattern::new(pattern))
+                .collect::<Result<_, _>>()?,
+        };
 
         // Get all containers from library and then filter them
         // Not perfect, but I got strange issues when trying to use `.get(id)`
@@ -53,63 +711,105 @@ impl Docker {
             .await?
             .into_iter()
             .filter_map(|container| {
-                match container.names.as_ref() {
-                    Some(names) => names.iter().find_map(|name| {
-                        self.containers
-                            .remove_entry(name)
-                            .map(|(_docker_name, display_name)| Container {
-                                name: display_name,
-                                summary: container.clone(),
-                            })
-                    }),
-                    _ => None,
+                let names = container.names.as_ref()?;
+                // Configured names take precedence over the patterns
+                if let Some(display_name) =
+                    names.iter().find_map(|name| self.containers.remove(name))
+                {
+                    return Some(Container {
+                        name: display_name,
+                        summary: container,
+                    });
+                }
+                if !selection.matches(&container) {
+                    return None;
                 }
-                // container.names.as_ref().map(|names| {
-                //     names.iter().find_map(|name| {
-                //         self.containers.remove_entry(name).map(|(_docker_name, display_name)| (display_name, container))
-                //     })
-                // })
+
+                // Below their project, containers are named after their Compose service
+                let service = container
+                    .labels
+                    .as_ref()
+                    .and_then(|labels| labels.get(SERVICE_LABEL))
+                    .filter(|_| self.group_projects);
+                let name = match service {
+                    Some(service) => service.to_string(),
+                    None => names.first()?.trim_start_matches('/').to_string(),
+                };
+                Some(Container {
+                    name,
+                    summary: container,
+                })
             })
             .collect();
 
-        for (docker_name, _display_name) in self.containers {
-            println!(
-                "{indent}{color}Warning: Could not find Docker container `{docker_name}'{reset}",
-                indent = " ".repeat(INDENT_WIDTH),
-                color = color::Fg(color::Yellow),
-                docker_name = docker_name,
-                reset = style::Reset
-            );
-        }
-
-        // Max length of all the container names (first column)
-        // to determine the padding
-        if let Some(max_len) = containers
-            .iter()
-            .map(|container| container.name.len())
-            .max()
-        {
-            for container in containers {
-                let status_color = match container.summary.state.as_deref() {
-                    Some("Created") | Some("Restarting") | Some("Paused") | Some("Removing")
-                    | Some("Configured") => color::Fg(color::Yellow).to_string(),
-                    Some("Running") => color::Fg(color::Green).to_string(),
-                    Some("Exited") => color::Fg(color::LightBlack).to_string(),
-                    Some("Dead") => color::Fg(color::Red).to_string(),
-                    _ => color::Fg(color::White).to_string(),
-                };
-                println!(
-                    "{indent}{name}: {padding}{color}{status}{reset}",
-                    indent = " ".repeat(INDENT_WIDTH),
-                    name = container.name,
-                    padding = " ".repeat(max_len - container.name.len()),
-                    color = status_color,
-                    status = container.summary.status.unwrap_or(String::from("?")),
-                    reset = style::Reset,
-                );
+        // Sampling the CPU usage takes a second, so all containers are sampled at once
+        let stats = join_all(containers.iter().map(|container| async {
+            match (
+                self.show_stats,
+                &container.summary.id,
+                container.summary.state.as_deref(),
+            ) {
+                (true, Some(id), Some(state)) if state.eq_ignore_ascii_case("running") => {
+                    get_stats(docker, id).await
+                }
+                _ => None,
             }
+        }))
+        .await;
+
+        let mut statuses = vec![];
+        for (container, stats) in containers.into_iter().zip(stats) {
+            // Health and restarts are only known when inspecting the container
+            let inspect = match (
+                self.show_health || self.show_restarts,
+                &container.summary.id,
+            ) {
+                (true, Some(id)) => Some(docker.containers().get(id).inspect().await?),
+                _ => None,
+            };
+            let health = inspect
+                .as_ref()
+                .and_then(|inspect| inspect.state.as_ref()?.health.as_ref()?.status.clone())
+                .filter(|health| self.show_health && health != "none");
+            let restart_count = inspect
+                .as_ref()
+                .and_then(|inspect| inspect.restart_count)
+                .filter(|_| self.show_restarts)
+                .map(|restart_count| restart_count as u64);
+
+            statuses.push(ContainerStatus {
+                name: container.name,
+                project: container
+                    .summary
+                    .labels
+                    .as_ref()
+                    .and_then(|labels| labels.get(PROJECT_LABEL))
+                    .cloned(),
+                // Docker reports states in lowercase, but not all compatible engines do
+                state: container.summary.state.map(|state| state.to_lowercase()),
+                status: container.summary.status,
+                health,
+                restart_count,
+                image: container.summary.image.filter(|_| self.show_image),
+                ports: match (self.show_ports, &container.summary.ports) {
+                    (true, Some(ports)) => format_ports(ports),
+                    _ => vec![],
+                },
+                stats,
+            });
         }
 
-        Ok(())
+        let disk_usage = match self.show_disk_usage {
+            true => Some(get_disk_usage(docker).await?),
+            false => None,
+        };
+
+        Ok(DockerOutput {
+            group_projects: self.group_projects,
+            stats_thresholds: self.stats_thresholds,
+            containers: statuses,
+            missing: self.containers.into_keys().collect(),
+            disk_usage,
+        })
     }
 }
@@ -1,84 +1,454 @@
 use crate::constants::INDENT_WIDTH;
 use async_trait::async_trait;
-use lazy_static::lazy_static;
-use regex::Regex;
-use serde::Deserialize;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
-use crate::command::{BetterCommand, BetterCommandError};
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::default_prepare;
+use crate::pickle::{self, PickleError, Value};
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Section};
+
+/// Terminator of every message on the socket, and the message that ends a session
+const END_COMMAND: &[u8] = b"<F2B_END_COMMAND>";
+const CLOSE_COMMAND: &[u8] = b"<F2B_CLOSE_COMMAND>";
+/// Format of the times in the output of `get <jail> banip --with-time`
+const BAN_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
 #[derive(Debug, Deserialize)]
 pub struct Fail2Ban {
+    /// Jails to show, all jails if empty
+    #[serde(default)]
     jails: Vec<String>,
+    /// Number of banned IPs to list per jail, most recently banned first
+    #[serde(default)]
+    recent_bans: usize,
+    /// Socket of the fail2ban server
+    #[serde(default = "default_socket")]
+    socket: PathBuf,
+}
+
+fn default_socket() -> PathBuf {
+    PathBuf::from("/var/run/fail2ban/fail2ban.sock")
 }
 
 #[async_trait]
 impl Component for Fail2Ban {
-    async fn print(self: Box<Self>, _global_config: &GlobalConfig, _width: Option<usize>) {
-        self.print_or_error()
-            .unwrap_or_else(|err| println!("Fail2Ban error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Fail2Ban error")
     }
     default_prepare!();
 }
 
-struct Entry {
+/// A banned IP, with the time of the ban if the server reports it
+#[derive(Serialize)]
+pub struct Ban {
+    ip: String,
+    time: Option<DateTime<Local>>,
+}
+
+/// Ban counts of one jail
+#[derive(Serialize)]
+pub struct JailStatus {
+    name: String,
     total: u32,
     current: u32,
+    recent: Vec<Ban>,
+}
+
+#[derive(Serialize)]
+pub struct Fail2BanOutput {
+    jails: Vec<JailStatus>,
+}
+
+impl ComponentOutput for Fail2BanOutput {
+    fn to_section(&self, global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("Fail2Ban"));
+        let indent = " ".repeat(INDENT_WIDTH);
+
+        for jail in &self.jails {
+            section.push(vec![Element::plain(format!("{}{}:", indent, jail.name))]);
+            section.push(vec![Element::plain(format!(
+                "{indent}{indent}Total bans:   {}",
+                jail.total
+            ))]);
+            section.push(vec![Element::plain(format!(
+                "{indent}{indent}Current bans: {}",
+                jail.current
+            ))]);
+
+            if jail.recent.is_empty() {
+                continue;
+            }
+            section.push(vec![Element::plain(format!(
+                "{indent}{indent}Recent bans:"
+            ))]);
+            let ip_padding = jail
+                .recent
+                .iter()
+                .map(|ban| ban.ip.len())
+                .max()
+                .unwrap_or(0);
+            for ban in &jail.recent {
+                let time = ban
+                    .time
+                    .map(|time| time.format(&global_config.time_format).to_string())
+                    .unwrap_or_default();
+                section.push(vec![Element::plain(
+                    format!(
+                        "{indent}{indent}{indent}{}{} {}",
+                        ban.ip,
+                        " ".repeat(ip_padding - ban.ip.len()),
+                        time
+                    )
+                    .trim_end(),
+                )]);
+            }
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        self.jails
+            .iter()
+            .flat_map(|jail| {
+                [
+                    Metric::new(
+                        "motd_fail2ban_current_bans",
+                        "Number of IPs currently banned in the jail",
+                        jail.current,
+                    ),
+                    Metric::new(
+                        "motd_fail2ban_total_bans",
+                        "Number of bans in the jail since fail2ban started",
+                        jail.total,
+                    ),
+                ]
+                .map(|metric| metric.label("jail", &jail.name))
+            })
+            .collect()
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum Fail2BanError {
-    #[error(transparent)]
-    BetterCommand(#[from] BetterCommandError),
+    #[error("Could not connect to the fail2ban socket {0}: {1}")]
+    Connect(String, std::io::Error),
 
-    #[error("Failed to parse int in output")]
-    ParseInt(#[from] std::num::ParseIntError),
+    #[error("fail2ban server error: {0}")]
+    Server(String),
+
+    #[error("Unexpected response from the fail2ban server: {0}")]
+    UnexpectedResponse(&'static str),
+
+    #[error("No jails found")]
+    NoJails,
+
+    #[error(transparent)]
+    Pickle(#[from] PickleError),
 
     #[error(transparent)]
     IO(#[from] std::io::Error),
 }
 
-fn get_jail_status(jail: &str) -> Result<Entry, Fail2BanError> {
-    lazy_static! {
-        static ref TOTAL_REGEX: Regex = Regex::new(r"Total banned:\s+([0-9]+)").unwrap();
-        static ref CURRENT_REGEX: Regex = Regex::new(r"Currently banned:\s+([0-9]+)").unwrap();
+/// Session on the socket of the fail2ban server, which is what `fail2ban-client` uses as well
+struct Client {
+    stream: UnixStream,
+}
+
+impl Client {
+    fn connect(socket: &PathBuf) -> Result<Self, Fail2BanError> {
+        let stream = UnixStream::connect(socket)
+            .map_err(|error| Fail2BanError::Connect(socket.display().to_string(), error))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+        Ok(Client { stream })
     }
 
-    let executable = "fail2ban-client";
-    let output = BetterCommand::new(executable)
-        .arg("status")
-        .arg(jail)
-        .check_status_and_get_output_string()?;
+    /// Send a command as a pickled list of words and return the result of the response
+    fn send(&mut self, command: &[&str]) -> Result<Value, Fail2BanError> {
+        self.stream.write_all(&pickle::dumps(command))?;
+        self.stream.write_all(END_COMMAND)?;
 
-    let total = TOTAL_REGEX.captures_iter(&output).next().unwrap()[1].parse::<u32>()?;
-    let current = CURRENT_REGEX.captures_iter(&output).next().unwrap()[1].parse::<u32>()?;
+        let mut response = vec![];
+        let mut buffer = [0; 4096];
+        while !response.ends_with(END_COMMAND) {
+            let len = self.stream.read(&mut buffer)?;
+            if len == 0 {
+                return Err(Fail2BanError::UnexpectedResponse("connection closed"));
+            }
+            response.extend_from_slice(&buffer[..len]);
+        }
+        response.truncate(response.len() - END_COMMAND.len());
+        parse_response(&response)
+    }
+}
+
+/// Result of a pickled `(code, result)` response, where a code other than 0 is an error
+fn parse_response(response: &[u8]) -> Result<Value, Fail2BanError> {
+    match pickle::loads(response)?.as_list() {
+        Some([code, result]) if code.as_int() == Some(0) => Ok(result.clone()),
+        Some([_, error]) => Err(Fail2BanError::Server(
+            error.as_str().unwrap_or("unknown error").to_string(),
+        )),
+        _ => Err(Fail2BanError::UnexpectedResponse(
+            "expected a code and a result",
+        )),
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self
+            .stream
+            .write_all(&[CLOSE_COMMAND, END_COMMAND].concat());
+    }
+}
+
+/// Value of a field in the nested lists of `(name, value)` pairs that `status` returns
+fn find<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    value
+        .as_list()?
+        .iter()
+        .find_map(|item| match item.as_list() {
+            Some([key, value]) if key.as_str() == Some(name) => Some(value),
+            _ => find(item, name),
+        })
+}
 
-    Ok(Entry { total, current })
+fn count(status: &Value, name: &'static str) -> Result<u32, Fail2BanError> {
+    find(status, name)
+        .and_then(Value::as_int)
+        .and_then(|count| u32::try_from(count).ok())
+        .ok_or(Fail2BanError::UnexpectedResponse(name))
+}
+
+/// Parse `<ip> \t<time of ban> + <ban time> = <end of ban>`
+fn parse_ban(line: &str) -> Option<Ban> {
+    let (ip, times) = line.split_once(char::is_whitespace)?;
+    let (time, _) = times.trim_start().split_once(" + ")?;
+    let time = NaiveDateTime::parse_from_str(time, BAN_TIME_FORMAT).ok()?;
+    Some(Ban {
+        ip: ip.to_string(),
+        time: Local.from_local_datetime(&time).earliest(),
+    })
 }
 
 impl Fail2Ban {
-    pub fn print_or_error(self) -> Result<(), Fail2BanError> {
-        println!("Fail2Ban:");
-
-        for jail in self.jails {
-            let entry = get_jail_status(&jail)?;
-            println!(
-                concat!(
-                    "{indent}{jail}:\n",
-                    "{indent}{indent}Total bans:   {total}\n",
-                    "{indent}{indent}Current bans: {current}",
-                ),
-                jail = jail,
-                total = entry.total,
-                current = entry.current,
-                indent = " ".repeat(INDENT_WIDTH),
+    fn get_jail_status(
+        &self,
+        client: &mut Client,
+        name: String,
+    ) -> Result<JailStatus, Fail2BanError> {
+        let status = client.send(&["status", &name])?;
+        let total = count(&status, "Total banned")?;
+        let current = count(&status, "Currently banned")?;
+
+        let mut recent = vec![];
+        if self.recent_bans > 0 {
+            // The times of bans are only available since fail2ban 0.11
+            recent = match client.send(&["get", &name, "banip", "--with-time"]) {
+                Ok(bans) => {
+                    let mut bans: Vec<Ban> = bans
+                        .as_list()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .filter_map(parse_ban)
+                        .collect();
+                    bans.sort_by_key(|ban| Reverse(ban.time));
+                    bans
+                }
+                // Without times, the list is in the order of the bans, oldest first
+                Err(Fail2BanError::Server(_)) => find(&status, "Banned IP list")
+                    .and_then(Value::as_list)
+                    .unwrap_or_default()
+                    .iter()
+                    .rev()
+                    .filter_map(Value::as_str)
+                    .map(|ip| Ban {
+                        ip: ip.to_string(),
+                        time: None,
+                    })
+                    .collect(),
+                Err(error) => return Err(error),
+            };
+            recent.truncate(self.recent_bans);
+        }
+
+        Ok(JailStatus {
+            name,
+            total,
+            current,
+            recent,
+        })
+    }
+
+    pub fn get_output_or_error(self) -> Result<Fail2BanOutput, Fail2BanError> {
+        let mut client = Client::connect(&self.socket)?;
+
+        let names = match self.jails.is_empty() {
+            true => find(&client.send(&["status"])?, "Jail list")
+                .and_then(Value::as_str)
+                .ok_or(Fail2BanError::UnexpectedResponse("Jail list"))?
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect(),
+            false => self.jails.clone(),
+        };
+        if names.is_empty() {
+            return Err(Fail2BanError::NoJails);
+        }
+
+        let jails = names
+            .into_iter()
+            .map(|name| self.get_jail_status(&mut client, name))
+            .collect::<Result<Vec<JailStatus>, Fail2BanError>>()?;
+
+        Ok(Fail2BanOutput { jails })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Responses to `status`, `status sshd` and `get sshd banip --with-time` as the server
+    // pickles them, with protocol 2 (Python 2) and 4 (Python 3, the same opcodes as 5)
+
+    const STATUS_2: &[u8] = b"\
+      \x80\x02K\x00]q\x00(X\x0e\x00\x00\x00Number of jailq\x01K\x02\x86q\x02X\x09\
+      \x00\x00\x00Jail listq\x03X\x0e\x00\x00\x00sshd, recidiveq\x04\x86q\x05e\x86\
+      q\x06.";
+
+    const JAIL_STATUS_2: &[u8] = b"\
+      \x80\x02K\x00]q\x00(X\x06\x00\x00\x00Filterq\x01]q\x02(X\x10\x00\x00\x00Curr\
+      ently failedq\x03K\x01\x86q\x04X\x0c\x00\x00\x00Total failedq\x05K\x0c\x86q\
+      \x06X\x09\x00\x00\x00File listq\x07]q\x08X\x11\x00\x00\x00/var/log/auth.logq\
+      \x09a\x86q\x0ae\x86q\x0bX\x07\x00\x00\x00Actionsq\x0c]q\x0d(X\x10\x00\x00\
+      \x00Currently bannedq\x0eK\x02\x86q\x0fX\x0c\x00\x00\x00Total bannedq\x10K\
+      \x07\x86q\x11X\x0e\x00\x00\x00Banned IP listq\x12]q\x13(cfail2ban.server.ipd\
+      ns\x0aIPAddr\x0aq\x14X\x09\x00\x00\x00192.0.2.1q\x15\x85q\x16\x81q\x17N}q\
+      \x18(X\x07\x00\x00\x00_familyq\x19K\x02X\x05\x00\x00\x00_addrq\x1aK{u\x86q\
+      \x1bbh\x14X\x0b\x00\x00\x002001:db8::1q\x1c\x85q\x1d\x81q\x1eN}q\x1f(h\x19K\
+      \x02h\x1aK{u\x86q be\x86q!e\x86q\x22e\x86q#.";
+
+    const BANIP_WITH_TIME_2: &[u8] = b"\
+      \x80\x02K\x00]q\x00(X:\x00\x00\x00192.0.2.1 \x092024-01-02 03:04:05 + 600 = \
+      2024-01-02 03:14:05q\x01X<\x00\x00\x002001:db8::1 \x092024-01-03 04:05:06 + \
+      600 = 2024-01-03 04:15:06q\x02e\x86q\x03.";
+
+    const STATUS_4: &[u8] = b"\
+      \x80\x04\x95=\x00\x00\x00\x00\x00\x00\x00K\x00]\x94(\x8c\x0eNumber of jail\
+      \x94K\x02\x86\x94\x8c\x09Jail list\x94\x8c\x0esshd, recidive\x94\x86\x94e\
+      \x86\x94.";
+
+    const JAIL_STATUS_4: &[u8] = b"\
+      \x80\x04\x95-\x01\x00\x00\x00\x00\x00\x00K\x00]\x94(\x8c\x06Filter\x94]\x94(\
+      \x8c\x10Currently failed\x94K\x01\x86\x94\x8c\x0cTotal failed\x94K\x0c\x86\
+      \x94\x8c\x09File list\x94]\x94\x8c\x11/var/log/auth.log\x94a\x86\x94e\x86\
+      \x94\x8c\x07Actions\x94]\x94(\x8c\x10Currently banned\x94K\x02\x86\x94\x8c\
+      \x0cTotal banned\x94K\x07\x86\x94\x8c\x0eBanned IP list\x94]\x94(\x8c\x15fai\
+      l2ban.server.ipdns\x94\x8c\x06IPAddr\x94\x93\x94\x8c\x09192.0.2.1\x94\x85\
+      \x94\x81\x94N}\x94(\x8c\x07_family\x94K\x02\x8c\x05_addr\x94K{u\x86\x94bh\
+      \x16\x8c\x0b2001:db8::1\x94\x85\x94\x81\x94N}\x94(h\x1bK\x02h\x1cK{u\x86\x94\
+      be\x86\x94e\x86\x94e\x86\x94.";
+
+    const BANIP_WITH_TIME_4: &[u8] = b"\
+      \x80\x04\x95\x85\x00\x00\x00\x00\x00\x00\x00K\x00]\x94(\x8c:192.0.2.1 \x0920\
+      24-01-02 03:04:05 + 600 = 2024-01-02 03:14:05\x94\x8c<2001:db8::1 \x092024-0\
+      1-03 04:05:06 + 600 = 2024-01-03 04:15:06\x94e\x86\x94.";
+
+    fn strings(values: &[Value]) -> Vec<&str> {
+        values.iter().filter_map(Value::as_str).collect()
+    }
+
+    #[test]
+    fn status() {
+        for response in [STATUS_2, STATUS_4] {
+            let status = parse_response(response).unwrap();
+            assert_eq!(
+                find(&status, "Jail list").and_then(Value::as_str),
+                Some("sshd, recidive")
+            );
+            assert_eq!(count(&status, "Number of jail").unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn jail_status() {
+        for response in [JAIL_STATUS_2, JAIL_STATUS_4] {
+            let status = parse_response(response).unwrap();
+            assert_eq!(count(&status, "Total failed").unwrap(), 12);
+            assert_eq!(count(&status, "Currently banned").unwrap(), 2);
+            assert_eq!(count(&status, "Total banned").unwrap(), 7);
+            assert!(count(&status, "Total unbanned").is_err());
+            // The IPs are instances of `IPAddr`, a subclass of `str`
+            let ips = find(&status, "Banned IP list")
+                .and_then(Value::as_list)
+                .unwrap();
+            assert_eq!(strings(ips), ["192.0.2.1", "2001:db8::1"]);
+        }
+    }
+
+    #[test]
+    fn bans_with_time() {
+        for response in [BANIP_WITH_TIME_2, BANIP_WITH_TIME_4] {
+            let bans = parse_response(response).unwrap();
+            let bans: Vec<Ban> = strings(bans.as_list().unwrap())
+                .into_iter()
+                .filter_map(parse_ban)
+                .collect();
+            let bans: Vec<(&str, String)> = bans
+                .iter()
+                .map(|ban| {
+                    (
+                        ban.ip.as_str(),
+                        ban.time.unwrap().format(BAN_TIME_FORMAT).to_string(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                bans,
+                [
+                    ("192.0.2.1", "2024-01-02 03:04:05".to_string()),
+                    ("2001:db8::1", "2024-01-03 04:05:06".to_string()),
+                ]
             );
         }
+        assert!(parse_ban("192.0.2.1").is_none());
+        assert!(parse_ban("192.0.2.1 \tyesterday + 600 = today").is_none());
+    }
 
-        Ok(())
+    #[test]
+    fn broken_responses_are_errors() {
+        for response in [STATUS_4, JAIL_STATUS_2, BANIP_WITH_TIME_4] {
+            for len in 0..response.len() {
+                assert!(parse_response(&response[..len]).is_err());
+            }
+        }
+        assert!(matches!(
+            parse_response(b"<F2B_END_COMMAND>"),
+            Err(Fail2BanError::Pickle(_))
+        ));
+        // A bare result without a code
+        assert!(matches!(
+            parse_response(&pickle::dumps(&["sshd"])),
+            Err(Fail2BanError::UnexpectedResponse(_))
+        ));
+        assert!(matches!(
+            parse_response(b"\x80\x04K\x01\x8c\x0cunknown jail\x86."),
+            Err(Fail2BanError::Server(error)) if error == "unknown jail"
+        ));
     }
 }
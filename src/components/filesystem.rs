@@ -1,24 +1,47 @@
 use async_trait::async_trait;
 use bytesize::ByteSize;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::cmp;
 use std::collections::HashMap;
 use std::iter;
 use systemstat::{Filesystem, Platform, System};
-use termion::{color, style};
 use thiserror::Error;
 
-use crate::component::{Component, Constraints, PrepareReturn};
+use crate::component::{
+    output_or_error, BoxedOutput, Component, ComponentOutput, Constraints, PrepareReturn,
+};
 use crate::config::global_config::GlobalConfig;
 use crate::constants::INDENT_WIDTH;
 use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Line, Section};
+use crate::severity::{Problem, Severity, UsageThresholds};
 
 const HEADER: [&str; 6] = ["Filesystems", "Device", "Mount", "Type", "Used", "Total"];
 
 /// A container for the mount points specified in the configuration file
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Filesystems {
-    pub mounts: HashMap<String, String>,
+    /// Thresholds for all filesystems that do not set their own
+    // Must come before `mounts`, which takes all remaining keys
+    #[serde(flatten)]
+    thresholds: UsageThresholds,
+    #[serde(flatten)]
+    mounts: HashMap<String, MountConfig>,
+}
+
+/// A filesystem in the configuration file,
+/// either just its mount point or a table with the mount point and thresholds
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum MountConfig {
+    MountPoint(String),
+    Detailed {
+        mount: String,
+        #[serde(flatten)]
+        thresholds: UsageThresholds,
+    },
 }
 
 #[async_trait]
@@ -29,36 +52,40 @@ impl Component for Filesystems {
             .unwrap_or((self, Some(Constraints { min_width: None })))
     }
 
-    // Print should never be called on a raw `Filesystems`
     // Prepare should be called, returning a `PreparedFilesystems`
-    async fn print(self: Box<Self>, global_config: &GlobalConfig, width: Option<usize>) {
-        let (prepared_filesystems, _) = self.prepare(global_config);
-        prepared_filesystems.print(global_config, width).await;
+    // If we get here, preparing failed, so do it again to report the error
+    async fn get_output(self: Box<Self>, global_config: &GlobalConfig) -> BoxedOutput {
+        match self.prepare_or_error(global_config) {
+            Ok((prepared_filesystems, _)) => prepared_filesystems.get_output(global_config).await,
+            Err(err) => output_or_error::<FilesystemsOutput, _>(Err(err), "Filesystem error"),
+        }
     }
 }
 
-/// A prepared, ready-to-print filesystems object
+/// A prepared filesystems object
 /// This is returned from the prepare phase
 /// It is generated based on the user's configuration stored in `Filesystems`
-/// and has all the information needed for printing
+/// and has all the information needed for the output
 struct PreparedFilesystems {
-    column_sizes: Vec<usize>,
     entries: Vec<Entry>,
-    bar_width: usize,
 }
 
 #[async_trait]
 impl Component for PreparedFilesystems {
-    async fn print(self: Box<Self>, global_config: &GlobalConfig, _width: Option<usize>) {
-        self.print_or_error(global_config).unwrap_or_else(|err| {
-            println!("Filesystem error: {}", err);
-        });
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        Box::new(FilesystemsOutput {
+            entries: self.entries,
+        })
     }
 
     default_prepare!();
 }
 
+#[derive(Serialize)]
+pub struct FilesystemsOutput {
+    entries: Vec<Entry>,
+}
+
 #[derive(Error, Debug)]
 pub enum FilesystemsError {
     #[error("Empty configuration for filesystems. Please remove the entire block to disable this component.")]
@@ -72,18 +99,41 @@ pub enum FilesystemsError {
 }
 
 /// Data needed to print one row of the filesystems table
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct Entry {
+    #[serde(rename = "name")]
     filesystem_name: String,
     dev: String,
     mount_point: String,
     fs_type: String,
-    used: String,
-    total: String,
+    /// Bytes
+    used: u64,
+    /// Bytes
+    total: u64,
     used_ratio: f64,
+    #[serde(skip)]
+    thresholds: UsageThresholds,
+}
+
+impl Entry {
+    /// The text in each column of the table
+    fn columns(&self) -> [String; 6] {
+        [
+            [" ".repeat(INDENT_WIDTH), self.filesystem_name.to_string()].concat(),
+            self.dev.to_string(),
+            self.mount_point.to_string(),
+            self.fs_type.to_string(),
+            ByteSize::b(self.used).to_string(),
+            ByteSize::b(self.total).to_string(),
+        ]
+    }
 }
 
-fn parse_into_entry(filesystem_name: String, mount: &Filesystem) -> Entry {
+fn parse_into_entry(
+    filesystem_name: String,
+    mount: &Filesystem,
+    thresholds: UsageThresholds,
+) -> Entry {
     let total = mount.total.as_u64();
     let avail = mount.avail.as_u64();
     let used = total - avail;
@@ -93,34 +143,60 @@ fn parse_into_entry(filesystem_name: String, mount: &Filesystem) -> Entry {
         mount_point: mount.fs_mounted_on.to_string(),
         dev: mount.fs_mounted_from.to_string(),
         fs_type: mount.fs_type.to_string(),
-        used: ByteSize::b(used).to_string(),
-        total: ByteSize::b(total).to_string(),
+        used,
+        total,
         used_ratio: (used as f64) / (total as f64),
+        thresholds,
     }
 }
 
-fn print_row<'a>(items: [&str; 6], column_sizes: impl IntoIterator<Item = &'a usize>) {
-    println!(
-        "{}",
+/// Width of each column of the table, the widest of the header and all entries
+fn column_sizes(entries: &[Entry]) -> Vec<usize> {
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .columns()
+                .iter()
+                .map(|x| x.len())
+                .collect::<Vec<usize>>()
+        })
+        .chain(iter::once(HEADER.iter().map(|x| x.len()).collect()))
+        .fold(vec![0; HEADER.len()], |acc, x| {
+            x.iter()
+                .zip(acc.iter())
+                .map(|(a, b)| cmp::max(a, b).to_owned())
+                .collect()
+        })
+}
+
+/// Width of the whole table, which is also the width of the bars (including prefix and suffix)
+fn display_width(column_sizes: &[usize]) -> usize {
+    // -2 because "Filesystems" does not count (it is not indented)
+    // and because zero indexed
+    column_sizes.iter().sum::<usize>() + (HEADER.len() - 2) * INDENT_WIDTH
+}
+
+fn format_row<'a, S: AsRef<str>>(
+    items: &[S],
+    column_sizes: impl IntoIterator<Item = &'a usize>,
+) -> Line {
+    vec![Element::plain(
         Itertools::intersperse(
             items
                 .iter()
                 .zip(column_sizes.into_iter())
-                .map(|(name, size)| format!("{: <size$}", name, size = size)),
-            " ".repeat(INDENT_WIDTH)
+                .map(|(name, size)| format!("{: <size$}", name.as_ref(), size = size)),
+            " ".repeat(INDENT_WIDTH),
         )
-        .collect::<String>()
-    );
+        .collect::<String>(),
+    )]
 }
 
 impl Filesystems {
-    pub fn new(mounts: HashMap<String, String>) -> Self {
-        Self { mounts }
-    }
-
     fn prepare_or_error(
         self,
-        global_config: &GlobalConfig,
+        _global_config: &GlobalConfig,
     ) -> Result<PrepareReturn, FilesystemsError> {
         let sys = System::new();
 
@@ -137,103 +213,98 @@ impl Filesystems {
         let entries = self
             .mounts
             .into_iter()
-            .map(
-                |(filesystem_name, mount_point)| match mounts.get(&mount_point) {
-                    Some(mount) => Ok(parse_into_entry(filesystem_name, mount)),
+            .map(|(filesystem_name, mount_config)| {
+                let (mount_point, thresholds) = match mount_config {
+                    MountConfig::MountPoint(mount_point) => (mount_point, self.thresholds),
+                    MountConfig::Detailed { mount, thresholds } => {
+                        (mount, thresholds.or(self.thresholds))
+                    }
+                };
+                match mounts.get(&mount_point) {
+                    Some(mount) => Ok(parse_into_entry(filesystem_name, mount, thresholds)),
                     _ => Err(FilesystemsError::MountNotFound { mount_point }),
-                },
-            )
-            .collect::<Result<Vec<Entry>, FilesystemsError>>()?;
-        let column_sizes = entries
-            .iter()
-            .map(|entry| {
-                vec![
-                    entry.filesystem_name.len() + INDENT_WIDTH,
-                    entry.dev.len(),
-                    entry.mount_point.len(),
-                    entry.fs_type.len(),
-                    entry.used.len(),
-                    entry.total.len(),
-                ]
+                }
             })
-            .chain(iter::once(HEADER.iter().map(|x| x.len()).collect()))
-            .fold(vec![0; HEADER.len()], |acc, x| {
-                x.iter()
-                    .zip(acc.iter())
-                    .map(|(a, b)| cmp::max(a, b).to_owned())
-                    .collect()
-            });
-
-        // -2 because "Filesystems" does not count (it is not indented)
-        // and because zero indexed
-        let bar_width = column_sizes.iter().sum::<usize>() + (HEADER.len() - 2) * INDENT_WIDTH
-            - global_config.progress_prefix.len()
-            - global_config.progress_suffix.len();
-        let fs_display_width =
-            bar_width + global_config.progress_prefix.len() + global_config.progress_suffix.len();
-
-        let prepared_filesystems = PreparedFilesystems {
-            bar_width,
-            column_sizes,
-            entries,
-        };
+            .collect::<Result<Vec<Entry>, FilesystemsError>>()?;
 
         let constraints = Constraints {
-            min_width: Some(fs_display_width),
+            min_width: Some(display_width(&column_sizes(&entries))),
         };
 
-        Ok((Box::new(prepared_filesystems), Some(constraints)))
+        Ok((Box::new(PreparedFilesystems { entries }), Some(constraints)))
     }
 }
 
-impl PreparedFilesystems {
-    fn print_or_error(self, global_config: &GlobalConfig) -> Result<(), FilesystemsError> {
-        print_row(HEADER, &self.column_sizes);
+impl ComponentOutput for FilesystemsOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let column_sizes = column_sizes(&self.entries);
+        let bar_width = display_width(&column_sizes);
 
-        for entry in self.entries {
-            let bar_full = ((self.bar_width as f64) * entry.used_ratio) as usize;
-            let bar_empty = self.bar_width - bar_full;
+        let mut section = Section::new(None);
+        section.push(format_row(&HEADER, &column_sizes));
 
-            print_row(
-                [
-                    &[" ".repeat(INDENT_WIDTH), entry.filesystem_name].concat(),
-                    &entry.dev[..],
-                    &entry.mount_point[..],
-                    &entry.fs_type[..],
-                    entry.used.as_str(),
-                    entry.total.as_str(),
-                ],
-                &self.column_sizes,
-            );
-
-            let full_color = match (entry.used_ratio * 100.0) as usize {
-                0..=75 => color::Fg(color::Green).to_string(),
-                76..=95 => color::Fg(color::Yellow).to_string(),
-                _ => color::Fg(color::Red).to_string(),
-            };
-
-            println!(
-                "{}",
+        for entry in &self.entries {
+            section.push(format_row(&entry.columns(), &column_sizes));
+
+            section.push(vec![
+                Element::plain(" ".repeat(INDENT_WIDTH)),
+                Element::bar(
+                    bar_width,
+                    entry.used_ratio,
+                    entry.thresholds.severity(entry.used_ratio).bar_color(),
+                ),
+            ]);
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        self.entries
+            .iter()
+            .flat_map(|entry| {
                 [
-                    " ".repeat(INDENT_WIDTH),
-                    global_config.progress_prefix.to_string(),
-                    full_color,
-                    global_config
-                        .progress_full_character
-                        .to_string()
-                        .repeat(bar_full),
-                    color::Fg(color::LightBlack).to_string(),
-                    global_config
-                        .progress_empty_character
-                        .to_string()
-                        .repeat(bar_empty),
-                    style::Reset.to_string(),
-                    global_config.progress_suffix.to_string(),
+                    Metric::new(
+                        "motd_filesystem_used_ratio",
+                        "Fraction of the filesystem that is used",
+                        entry.used_ratio,
+                    ),
+                    Metric::new(
+                        "motd_filesystem_used_bytes",
+                        "Used space of the filesystem",
+                        entry.used as f64,
+                    ),
+                    Metric::new(
+                        "motd_filesystem_size_bytes",
+                        "Total space of the filesystem",
+                        entry.total as f64,
+                    ),
                 ]
-                .join("")
-            );
-        }
+                .map(|metric| {
+                    metric
+                        .label("name", &entry.filesystem_name)
+                        .label("mount", &entry.mount_point)
+                })
+            })
+            .collect()
+    }
 
-        Ok(())
+    fn problems(&self) -> Vec<Problem> {
+        self.entries
+            .iter()
+            .map(|entry| (entry, entry.thresholds.severity(entry.used_ratio)))
+            .filter(|(_, severity)| *severity != Severity::Ok)
+            .map(|(entry, severity)| {
+                Problem::new(
+                    severity,
+                    format!(
+                        "{} ({}) is {:.0}% full",
+                        entry.filesystem_name,
+                        entry.mount_point,
+                        entry.used_ratio * 100.0
+                    ),
+                )
+            })
+            .collect()
     }
 }
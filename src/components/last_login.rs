@@ -1,31 +1,43 @@
 use async_trait::async_trait;
-use chrono::{Local, TimeZone};
+use chrono::{DateTime, Local, TimeZone};
 use humantime::format_duration;
 use last_rs::{get_logins, Enter, Exit, LastError};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
 use std::time::Duration;
-use termion::{color, style};
 use thiserror::Error;
 use time::error::Format as TimeFormatError;
 use time::error::IndeterminateOffset as TimeIndeterminateOffsetError;
 use time::error::InvalidFormatDescription as TimeInvalidFormatDescriptionError;
 
-use crate::command::BetterCommandError;
-use crate::component::Component;
+use crate::command::{BetterCommand, BetterCommandError};
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::constants::INDENT_WIDTH;
 use crate::default_prepare;
+use crate::render::json::serialize_seconds;
+use crate::section::{Color, Element, Line, Section};
 
+/// Records of failed logins, and the size of one record of `struct utmp` in it on Linux
+const BTMP: &str = "/var/log/btmp";
+const UTMP_SIZE: usize = 384;
+
+#[derive(Debug, Deserialize)]
 pub struct LastLogin {
-    pub users: HashMap<String, usize>,
+    /// Show the previous session of the user running rust-motd and the failed logins since
+    #[serde(default)]
+    current_user: bool,
+    #[serde(flatten)]
+    users: HashMap<String, usize>,
 }
 
 #[async_trait]
 impl Component for LastLogin {
-    async fn print(self: Box<Self>, global_config: &GlobalConfig, _width: Option<usize>) {
-        self.print_or_error(global_config)
-            .unwrap_or_else(|err| println!("Last login error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Last login error")
     }
     default_prepare!();
 }
@@ -54,81 +66,287 @@ pub enum LastLoginError {
     TimeIndeterminateOffset(#[from] TimeIndeterminateOffsetError),
 }
 
-fn format_entry(
-    entry: &Enter,
-    longest_location: usize,
-    time_format: &str,
-) -> Result<String, LastLoginError> {
-    let location = format!("{:>width$}", entry.host, width = longest_location);
+/// How a session ended
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SessionEnd {
+    Logout {
+        #[serde(rename = "duration_seconds", serialize_with = "serialize_seconds")]
+        duration: Duration,
+    },
+    StillLoggedIn,
+    Crash,
+    Reboot,
+}
+
+/// One login session of a user
+#[derive(Serialize)]
+struct Session {
+    host: String,
+    login_time: DateTime<Local>,
+    end: SessionEnd,
+}
+
+/// A failed login, as recorded in btmp
+#[derive(Serialize)]
+struct FailedLogin {
+    host: String,
+    time: DateTime<Local>,
+}
+
+/// Failed logins of a user since their previous session
+#[derive(Serialize)]
+struct FailedLogins {
+    count: usize,
+    last: Option<FailedLogin>,
+}
+
+/// The most recent sessions of one user
+#[derive(Serialize)]
+struct UserSessions {
+    username: String,
+    sessions: Vec<Session>,
+    /// Only for the current user, if btmp is readable
+    failed_logins: Option<FailedLogins>,
+}
+
+#[derive(Serialize)]
+pub struct LastLoginOutput {
+    users: Vec<UserSessions>,
+}
+
+fn parse_session(entry: &Enter) -> Session {
     let login_time = entry.login_time;
 
-    let exit = match entry.exit {
+    let end = match entry.exit {
         Exit::Logout(time) => {
             // Timezone does not matter here
             // Were taking the difference of two times with the same offset
             let delta_time = time - login_time;
-            let delta_time = Duration::new((delta_time.whole_seconds() as u64 / 60) * 60, 0);
-            format_duration(delta_time).to_string()
-        }
-        _ => {
-            let (colour, message) = match entry.exit {
-                Exit::StillLoggedIn => (color::Fg(color::Green).to_string(), "still logged in"),
-                Exit::Crash(_) => (color::Fg(color::Yellow).to_string(), "crash"),
-                Exit::Reboot(_) => (color::Fg(color::Yellow).to_string(), "down"),
-                Exit::Logout(_) => unreachable!(),
-            };
-            format!("{}{}{}", colour, message, style::Reset)
+            SessionEnd::Logout {
+                duration: Duration::new((delta_time.whole_seconds() as u64 / 60) * 60, 0),
+            }
         }
+        Exit::StillLoggedIn => SessionEnd::StillLoggedIn,
+        Exit::Crash(_) => SessionEnd::Crash,
+        Exit::Reboot(_) => SessionEnd::Reboot,
     };
 
-    Ok(format!(
-        "{indent}from {location} at {login_time} ({exit})",
-        location = location,
+    Session {
+        host: entry.host.to_string(),
         // There has to be a better way to go from a time OffsetDateTime to a
         // chrono DateTime
-        login_time = Local
-            .timestamp(login_time.unix_timestamp(), 0)
-            .format(time_format),
-        exit = exit,
-        indent = " ".repeat(2 * INDENT_WIDTH),
-    ))
+        login_time: Local.timestamp(login_time.unix_timestamp(), 0),
+        end,
+    }
 }
 
-impl LastLogin {
-    pub fn print_or_error(self, global_config: &GlobalConfig) -> Result<(), LastLoginError> {
-        println!("Last Login:");
+fn format_session(session: &Session, longest_location: usize, time_format: &str) -> Line {
+    let location = format!("{:>width$}", session.host, width = longest_location);
 
-        for (username, num_logins) in self.users {
-            println!("{}{}:", " ".repeat(INDENT_WIDTH), username);
-            let entries = get_logins("/var/log/wtmp")?
-                .into_iter()
-                .filter(|entry| entry.user == username)
-                .take(num_logins)
-                .collect::<Vec<Enter>>();
+    let exit = match session.end {
+        SessionEnd::Logout { duration } => Element::plain(format_duration(duration).to_string()),
+        SessionEnd::StillLoggedIn => Element::colored("still logged in", Color::Green),
+        SessionEnd::Crash => Element::colored("crash", Color::Yellow),
+        SessionEnd::Reboot => Element::colored("down", Color::Yellow),
+    };
 
-            let longest_location = entries.iter().map(|entry| entry.host.len()).max();
+    vec![
+        Element::plain(format!(
+            "{indent}from {location} at {login_time} (",
+            location = location,
+            login_time = session.login_time.format(time_format),
+            indent = " ".repeat(2 * INDENT_WIDTH),
+        )),
+        exit,
+        Element::plain(")"),
+    ]
+}
+
+impl ComponentOutput for LastLoginOutput {
+    fn to_section(&self, global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("Last Login"));
+
+        for user in &self.users {
+            section.push(vec![Element::plain(format!(
+                "{}{}:",
+                " ".repeat(INDENT_WIDTH),
+                user.username
+            ))]);
+
+            let longest_location = user.sessions.iter().map(|session| session.host.len()).max();
             match longest_location {
                 Some(longest_location) => {
-                    let formatted_entries = entries.iter().map(|entry| {
-                        format_entry(entry, longest_location, &global_config.time_format)
-                    });
-                    for entry in formatted_entries {
-                        match entry {
-                            Ok(x) => println!("{}", x),
-                            Err(err) => println!("{}", err),
-                        }
+                    for session in &user.sessions {
+                        section.push(format_session(
+                            session,
+                            longest_location,
+                            &global_config.time_format,
+                        ));
                     }
                 }
-                None => println!(
-                    "{indent}{color}No logins found for `{username}'{reset}",
-                    indent = " ".repeat(2 * INDENT_WIDTH),
-                    username = username,
-                    color = color::Fg(color::Red),
-                    reset = style::Reset,
-                ),
+                None => section.push(vec![
+                    Element::plain(" ".repeat(2 * INDENT_WIDTH)),
+                    Element::colored(
+                        format!("No logins found for `{}'", user.username),
+                        Color::Red,
+                    ),
+                ]),
+            }
+
+            if let Some(FailedLogins {
+                count,
+                last: Some(last),
+            }) = &user.failed_logins
+            {
+                let indent = " ".repeat(2 * INDENT_WIDTH);
+                section.push(vec![Element::plain(format!(
+                    "{indent}Last failed login from {} at {}",
+                    last.host,
+                    last.time.format(&global_config.time_format)
+                ))]);
+                section.push(vec![
+                    Element::plain(indent),
+                    Element::colored(
+                        format!(
+                            "There {} {} failed login attempt{} since the last successful login",
+                            if *count == 1 { "was" } else { "were" },
+                            count,
+                            if *count == 1 { "" } else { "s" }
+                        ),
+                        Color::Yellow,
+                    ),
+                ]);
+            }
+        }
+
+        section
+    }
+}
+
+/// Name of the user running rust-motd, as for `whoami`
+pub fn current_user() -> Result<String, BetterCommandError> {
+    match env::var("USER").or_else(|_| env::var("LOGNAME")) {
+        Ok(user) if !user.is_empty() => Ok(user),
+        _ => Ok(BetterCommand::new("id")
+            .arg("-un")
+            .check_status_and_get_output_string()?
+            .trim()
+            .to_string()),
+    }
+}
+
+/// The previous session of `user`, not counting the session they are in now
+fn previous_session<'a>(logins: &'a [Enter], user: &str) -> Option<&'a Enter> {
+    let mut sessions = logins.iter().filter(|entry| entry.user == user).peekable();
+    if sessions
+        .peek()
+        .is_some_and(|entry| matches!(entry.exit, Exit::StillLoggedIn))
+    {
+        sessions.next();
+    }
+    sessions.next()
+}
+
+/// Start of the previous session of `user`, not counting the session they are in now
+pub fn previous_login(user: &str) -> Result<Option<DateTime<Local>>, LastError> {
+    let logins = get_logins("/var/log/wtmp")?;
+    Ok(previous_session(&logins, user).and_then(|entry| {
+        Local
+            .timestamp_opt(entry.login_time.unix_timestamp(), 0)
+            .single()
+    }))
+}
+
+/// Text field of a utmp record, which is padded with zeros
+fn utmp_string(bytes: &[u8]) -> String {
+    let len = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+/// Failed logins of `user` since `since`, or `None` if btmp is not readable, which it is only for root
+///
+/// last-rs cannot be used here, since it leaves out the user of these records,
+/// and fails on usernames that are not valid UTF-8, which are common in failed logins
+fn get_failed_logins(
+    user: &str,
+    since: Option<DateTime<Local>>,
+) -> Result<Option<FailedLogins>, LastLoginError> {
+    let btmp = match fs::read(BTMP) {
+        Ok(btmp) => btmp,
+        Err(error)
+            if matches!(
+                error.kind(),
+                ErrorKind::NotFound | ErrorKind::PermissionDenied
+            ) =>
+        {
+            return Ok(None)
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    // Fields of `struct utmp` after the type, pid, line and id
+    let failed_logins: Vec<FailedLogin> = btmp
+        .chunks_exact(UTMP_SIZE)
+        .filter(|record| utmp_string(&record[44..76]) == user)
+        .filter_map(|record| {
+            let seconds = i32::from_le_bytes(record[340..344].try_into().ok()?);
+            Some(FailedLogin {
+                host: utmp_string(&record[76..332]),
+                time: Local.timestamp_opt(seconds.into(), 0).single()?,
+            })
+        })
+        .filter(|failed_login| since.is_none_or(|since| failed_login.time > since))
+        .collect();
+
+    Ok(Some(FailedLogins {
+        count: failed_logins.len(),
+        last: failed_logins
+            .into_iter()
+            .max_by_key(|failed_login| failed_login.time),
+    }))
+}
+
+impl LastLogin {
+    pub fn get_output_or_error(self) -> Result<LastLoginOutput, LastLoginError> {
+        let logins = get_logins("/var/log/wtmp")?;
+
+        let mut users = vec![];
+        if self.current_user {
+            let username = current_user()?;
+            let previous = previous_session(&logins, &username);
+            let since = previous.and_then(|entry| {
+                Local
+                    .timestamp_opt(entry.login_time.unix_timestamp(), 0)
+                    .single()
+            });
+            users.push(UserSessions {
+                failed_logins: get_failed_logins(&username, since)?,
+                sessions: previous.map(parse_session).into_iter().collect(),
+                username,
+            });
+        }
+
+        for (username, num_logins) in self.users {
+            if users.iter().any(|user| user.username == username) {
+                continue;
             }
+            let sessions = logins
+                .iter()
+                .filter(|entry| entry.user == username)
+                .take(num_logins)
+                .map(parse_session)
+                .collect();
+            users.push(UserSessions {
+                username,
+                sessions,
+                failed_logins: None,
+            });
         }
 
-        Ok(())
+        Ok(LastLoginOutput { users })
     }
 }
@@ -1,24 +1,49 @@
 use async_trait::async_trait;
-use chrono::Local;
-use serde::Deserialize;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Section};
 
 #[derive(Debug, Deserialize)]
 pub struct LastRun {}
 
 #[async_trait]
 impl Component for LastRun {
-    async fn print(self: Box<Self>, global_config: &GlobalConfig, _width: Option<usize>) {
-        self.print_or_error(global_config)
-            .unwrap_or_else(|err| println!("Last run error: {}", err));
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Last run error")
     }
     default_prepare!();
 }
 
+#[derive(Serialize)]
+pub struct LastRunOutput {
+    time: DateTime<Local>,
+}
+
+impl ComponentOutput for LastRunOutput {
+    fn to_section(&self, global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(None);
+        section.push(vec![Element::plain(format!(
+            "Last updated: {}",
+            self.time.format(&global_config.time_format)
+        ))]);
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![Metric::new(
+            "motd_last_run_timestamp",
+            "Time rust-motd was run in seconds since the epoch",
+            self.time.timestamp() as f64,
+        )]
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum LastRunError {
     #[error(transparent)]
@@ -29,11 +54,7 @@ pub enum LastRunError {
 }
 
 impl LastRun {
-    pub fn print_or_error(self, global_config: &GlobalConfig) -> Result<(), LastRunError> {
-        println!(
-            "Last updated: {}",
-            Local::now().format(&global_config.time_format)
-        );
-        Ok(())
+    pub fn get_output_or_error(self) -> Result<LastRunOutput, LastRunError> {
+        Ok(LastRunOutput { time: Local::now() })
     }
 }
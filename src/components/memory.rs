@@ -1,25 +1,31 @@
 use async_trait::async_trait;
-use serde::Deserialize;
-use systemstat::{saturating_sub_bytes, Platform, System};
-use termion::{color, style};
+use serde::{Deserialize, Serialize};
+use systemstat::{saturating_sub_bytes, ByteSize, Platform, System};
 use thiserror::Error;
 
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::constants::INDENT_WIDTH;
 use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Section};
+use crate::severity::{Problem, Severity, UsageThresholds};
 
 #[derive(Debug, Deserialize)]
 pub struct Memory {
     swap_pos: SwapPosition,
+    /// Thresholds for RAM and swap
+    #[serde(flatten)]
+    thresholds: UsageThresholds,
+    /// Thresholds for swap only, overriding `thresholds`
+    #[serde(default)]
+    swap_thresholds: UsageThresholds,
 }
 
 #[async_trait]
 impl Component for Memory {
-    async fn print(self: Box<Self>, global_config: &GlobalConfig, width: Option<usize>) {
-        self.print_or_error(global_config, width)
-            .unwrap_or_else(|err| println!("Memory error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Memory error")
     }
     default_prepare!();
 }
@@ -47,11 +53,31 @@ enum SwapPosition {
     None,
 }
 
+#[derive(Serialize)]
 struct MemoryUsage {
+    #[serde(skip)]
     name: String,
-    used: String,
-    total: String,
+    /// Bytes
+    used: u64,
+    /// Bytes
+    total: u64,
     used_ratio: f64,
+    #[serde(skip)]
+    thresholds: UsageThresholds,
+}
+
+impl MemoryUsage {
+    fn severity(&self) -> Severity {
+        self.thresholds.severity(self.used_ratio)
+    }
+}
+
+#[derive(Serialize)]
+pub struct MemoryOutput {
+    #[serde(skip)]
+    swap_pos: SwapPosition,
+    ram: MemoryUsage,
+    swap: Option<MemoryUsage>,
 }
 
 impl MemoryUsage {
@@ -61,6 +87,7 @@ impl MemoryUsage {
         sys: &System,
         free_name: &str,
         total_name: &str,
+        thresholds: UsageThresholds,
     ) -> Result<Self, MemoryError> {
         let memory = sys.memory()?;
         let total =
@@ -83,9 +110,10 @@ impl MemoryUsage {
         let used = saturating_sub_bytes(*total, *free);
         Ok(MemoryUsage {
             name,
-            used: used.to_string(),
-            total: total.to_string(),
+            used: used.as_u64(),
+            total: total.as_u64(),
             used_ratio: used.as_u64() as f64 / total.as_u64() as f64,
+            thresholds,
         })
     }
 
@@ -95,128 +123,150 @@ impl MemoryUsage {
         sys: &System,
         free_name: &str,
         total_name: &str,
+        thresholds: UsageThresholds,
     ) -> Result<Self, MemoryError> {
         Err(MemoryError::UnsupportedPlatform)
     }
 }
 
-fn format_bar(
-    global_config: &GlobalConfig,
-    width: usize,
-    full_ratio: f64,
-    full_color: String,
-) -> String {
-    let without_ends_width =
-        width - global_config.progress_suffix.len() - global_config.progress_prefix.len();
-
-    let bar_full = ((without_ends_width as f64) * full_ratio) as usize;
-    let bar_empty = without_ends_width - bar_full;
-
-    [
-        global_config.progress_prefix.to_string(),
-        full_color,
-        global_config
-            .progress_full_character
-            .to_string()
-            .repeat(bar_full),
-        color::Fg(color::LightBlack).to_string(),
-        global_config
-            .progress_empty_character
-            .to_string()
-            .repeat(bar_empty),
-        style::Reset.to_string(),
-        global_config.progress_suffix.to_string(),
-    ]
-    .join("")
+fn label(entry: &MemoryUsage) -> String {
+    format!(
+        "{}: {} / {}",
+        entry.name,
+        ByteSize::b(entry.used),
+        ByteSize::b(entry.total)
+    )
 }
 
-fn full_color(ratio: f64) -> String {
-    match (ratio * 100.) as usize {
-        0..=75 => color::Fg(color::Green).to_string(),
-        76..=95 => color::Fg(color::Yellow).to_string(),
-        _ => color::Fg(color::Red).to_string(),
-    }
-}
-
-fn print_stacked(entries: Vec<MemoryUsage>, width: usize, global_config: &GlobalConfig) {
+fn push_stacked(section: &mut Section, entries: &[&MemoryUsage], width: usize) {
     for entry in entries {
-        println!(
-            "{}{}: {} / {}",
+        section.push(vec![Element::plain(format!(
+            "{}{}",
             " ".repeat(INDENT_WIDTH),
-            entry.name,
-            entry.used,
-            entry.total
-        );
-        let full_color = full_color(entry.used_ratio);
-        let bar = format_bar(global_config, width, entry.used_ratio, full_color);
-        println!(
-            "{indent}{bar}",
-            indent = " ".repeat(INDENT_WIDTH),
-            bar = bar
-        );
+            label(entry)
+        ))]);
+        section.push(vec![
+            Element::plain(" ".repeat(INDENT_WIDTH)),
+            Element::bar(width, entry.used_ratio, entry.severity().bar_color()),
+        ]);
     }
 }
 
-impl Memory {
-    pub fn print_or_error(
-        self,
-        global_config: &GlobalConfig,
-        width: Option<usize>,
-    ) -> Result<(), MemoryError> {
-        let sys = System::new();
+impl ComponentOutput for MemoryOutput {
+    fn to_section(&self, global_config: &GlobalConfig, width: Option<usize>) -> Section {
         let width = width.unwrap_or(global_config.progress_width - INDENT_WIDTH);
+        let mut section = Section::new(Some("Memory"));
 
-        let ram_usage =
-            MemoryUsage::get_by_name("RAM".to_string(), &sys, "MemAvailable", "MemTotal")?;
-        println!("Memory");
-        match self.swap_pos {
-            SwapPosition::None => print_stacked(vec![ram_usage], width, global_config),
-            SwapPosition::Below => {
-                let swap_usage =
-                    MemoryUsage::get_by_name("Swap".to_string(), &sys, "SwapFree", "SwapTotal")?;
-                print_stacked(vec![ram_usage, swap_usage], width, global_config)
-            }
-            SwapPosition::Beside => {
-                let swap_usage =
-                    MemoryUsage::get_by_name("Swap".to_string(), &sys, "SwapFree", "SwapTotal")?;
-
+        match (&self.swap_pos, &self.swap) {
+            (SwapPosition::Beside, Some(swap_usage)) => {
+                let ram_usage = &self.ram;
                 let min_spacing = 1;
                 let bar_width = (width - min_spacing) / 2;
                 let spacing = width - 2 * bar_width;
                 let spacing = " ".repeat(spacing);
 
-                let ram_label = format!(
-                    "{}: {} / {}",
-                    ram_usage.name, ram_usage.used, ram_usage.total
-                );
-                let swap_label = format!(
-                    "{}: {} / {}",
-                    swap_usage.name, swap_usage.used, swap_usage.total
-                );
-                println!(
+                section.push(vec![Element::plain(format!(
                     "{}{ram_label:padding$}{spacing}{swap_label}",
                     " ".repeat(INDENT_WIDTH),
-                    ram_label = ram_label,
+                    ram_label = label(ram_usage),
                     padding = bar_width,
                     spacing = spacing,
-                    swap_label = swap_label
-                );
-                let bar_color = full_color(ram_usage.used_ratio);
-                let ram_bar = format_bar(global_config, bar_width, ram_usage.used_ratio, bar_color);
-
-                let bar_color = full_color(swap_usage.used_ratio);
-                let swap_bar =
-                    format_bar(global_config, bar_width, swap_usage.used_ratio, bar_color);
-                println!(
-                    "{indent}{ram}{spacing}{swap}",
-                    indent = " ".repeat(INDENT_WIDTH),
-                    ram = ram_bar,
-                    spacing = spacing,
-                    swap = swap_bar
-                );
+                    swap_label = label(swap_usage)
+                ))]);
+                section.push(vec![
+                    Element::plain(" ".repeat(INDENT_WIDTH)),
+                    Element::bar(
+                        bar_width,
+                        ram_usage.used_ratio,
+                        ram_usage.severity().bar_color(),
+                    ),
+                    Element::plain(spacing),
+                    Element::bar(
+                        bar_width,
+                        swap_usage.used_ratio,
+                        swap_usage.severity().bar_color(),
+                    ),
+                ]);
+            }
+            (_, swap_usage) => {
+                let entries: Vec<&MemoryUsage> =
+                    std::iter::once(&self.ram).chain(swap_usage).collect();
+                push_stacked(&mut section, &entries, width);
             }
         }
 
-        Ok(())
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let mut metrics = vec![
+            Metric::new(
+                "motd_memory_used_bytes",
+                "Used memory",
+                self.ram.used as f64,
+            ),
+            Metric::new(
+                "motd_memory_total_bytes",
+                "Total memory",
+                self.ram.total as f64,
+            ),
+        ];
+        if let Some(swap) = &self.swap {
+            metrics.push(Metric::new(
+                "motd_swap_used_bytes",
+                "Used swap",
+                swap.used as f64,
+            ));
+            metrics.push(Metric::new(
+                "motd_swap_total_bytes",
+                "Total swap",
+                swap.total as f64,
+            ));
+        }
+        metrics
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        std::iter::once(&self.ram)
+            .chain(&self.swap)
+            .map(|usage| (usage, usage.severity()))
+            .filter(|(_, severity)| *severity != Severity::Ok)
+            .map(|(usage, severity)| {
+                Problem::new(
+                    severity,
+                    format!("{} is {:.0}% full", usage.name, usage.used_ratio * 100.0),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Memory {
+    pub fn get_output_or_error(self) -> Result<MemoryOutput, MemoryError> {
+        let sys = System::new();
+
+        let ram = MemoryUsage::get_by_name(
+            "RAM".to_string(),
+            &sys,
+            "MemAvailable",
+            "MemTotal",
+            self.thresholds,
+        )?;
+        let swap = match self.swap_pos {
+            SwapPosition::None => None,
+            SwapPosition::Below | SwapPosition::Beside => Some(MemoryUsage::get_by_name(
+                "Swap".to_string(),
+                &sys,
+                "SwapFree",
+                "SwapTotal",
+                self.swap_thresholds.or(self.thresholds),
+            )?),
+        };
+
+        Ok(MemoryOutput {
+            swap_pos: self.swap_pos,
+            ram,
+            swap,
+        })
     }
 }
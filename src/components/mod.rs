@@ -1,11 +1,18 @@
 pub mod banner;
+pub mod cpu;
 pub mod docker;
 pub mod fail_2_ban;
 pub mod filesystem;
 pub mod last_login;
 pub mod last_run;
 pub mod memory;
+pub mod network;
+pub mod reboot_required;
+pub mod sensors;
 pub mod service_status;
+pub mod ssh_failures;
 pub mod ssl_certs;
+pub mod systemd_timers;
+pub mod updates;
 pub mod uptime;
 pub mod weather;
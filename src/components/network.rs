@@ -0,0 +1,309 @@
+use async_trait::async_trait;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net;
+use std::path::Path;
+use systemstat::{ByteSize, IpAddr, Platform, System};
+use thiserror::Error;
+
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
+use crate::config::global_config::GlobalConfig;
+use crate::constants::INDENT_WIDTH;
+use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+use crate::severity::{Problem, Severity};
+
+const NET_PATH: &str = "/sys/class/net";
+
+/// `ARPHRD_LOOPBACK`, the hardware type of loopback interfaces
+const LOOPBACK_TYPE: &str = "772";
+
+#[derive(Debug, Deserialize)]
+pub struct Network {
+    /// Glob patterns of the interfaces to show, all if empty
+    #[serde(default)]
+    interfaces: Vec<String>,
+    /// Glob patterns of interfaces to hide
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default = "default_true")]
+    hide_loopback: bool,
+    /// Hide interfaces that are not backed by a device, like bridges, veths and tunnels
+    #[serde(default)]
+    hide_virtual: bool,
+    /// Show IPv6 link-local addresses (`fe80::/10`)
+    #[serde(default)]
+    show_link_local: bool,
+    /// Show the number of bytes received and transmitted
+    #[serde(default = "default_true")]
+    show_traffic: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[async_trait]
+impl Component for Network {
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Network error")
+    }
+    default_prepare!();
+}
+
+#[derive(Error, Debug)]
+pub enum NetworkError {
+    #[error(transparent)]
+    Pattern(#[from] glob::PatternError),
+
+    #[error("No network interfaces found")]
+    NoInterfaces,
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Serialize)]
+struct Interface {
+    name: String,
+    /// Operational state as reported by the kernel, for example `up`, `down` or `unknown`
+    state: String,
+    /// Addresses in CIDR notation
+    addresses: Vec<String>,
+    rx_bytes: u64,
+    tx_bytes: u64,
+    /// Whether the interface was selected through `interfaces`, so it is expected to be up
+    #[serde(skip)]
+    selected: bool,
+}
+
+impl Interface {
+    fn severity(&self) -> Severity {
+        match self.state.as_str() {
+            "down" | "lowerlayerdown" if self.selected => Severity::Warning,
+            _ => Severity::Ok,
+        }
+    }
+
+    fn traffic(&self) -> String {
+        format!(
+            "RX {}  TX {}",
+            ByteSize::b(self.rx_bytes),
+            ByteSize::b(self.tx_bytes)
+        )
+    }
+}
+
+#[derive(Serialize)]
+pub struct NetworkOutput {
+    #[serde(skip)]
+    show_traffic: bool,
+    interfaces: Vec<Interface>,
+}
+
+impl ComponentOutput for NetworkOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("Network"));
+
+        let column_width = |column: &dyn Fn(&Interface) -> String| {
+            self.interfaces
+                .iter()
+                .map(|interface| column(interface).chars().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let name_width = column_width(&|interface| interface.name.clone());
+        let state_width = column_width(&|interface| interface.state.clone());
+        let traffic_width = column_width(&Interface::traffic);
+
+        for interface in &self.interfaces {
+            let state_color = match (interface.state.as_str(), interface.severity()) {
+                ("up", _) => Color::Green,
+                (_, Severity::Ok) => Color::LightBlack,
+                (_, severity) => severity.bar_color(),
+            };
+            // The columns after the state, padded so they line up
+            let mut rest = String::new();
+            if self.show_traffic {
+                rest.push_str(&format!(
+                    "  {:traffic_width$}",
+                    interface.traffic(),
+                    traffic_width = traffic_width
+                ));
+            }
+            rest.push_str(&format!("  {}", interface.addresses.join(", ")));
+            let rest = rest.trim_end();
+
+            let mut line = vec![
+                Element::plain(format!(
+                    "{}{:name_width$}  ",
+                    " ".repeat(INDENT_WIDTH),
+                    interface.name,
+                    name_width = name_width
+                )),
+                Element::colored(
+                    match rest.is_empty() {
+                        true => interface.state.clone(),
+                        false => format!(
+                            "{:state_width$}",
+                            interface.state,
+                            state_width = state_width
+                        ),
+                    },
+                    state_color,
+                ),
+            ];
+            if !rest.is_empty() {
+                line.push(Element::plain(rest));
+            }
+            section.push(line);
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        self.interfaces
+            .iter()
+            .flat_map(|interface| {
+                [
+                    Metric::new(
+                        "motd_network_up",
+                        "Whether the network interface is up",
+                        u8::from(interface.state == "up"),
+                    ),
+                    Metric::new(
+                        "motd_network_receive_bytes",
+                        "Bytes received by the network interface",
+                        interface.rx_bytes as f64,
+                    ),
+                    Metric::new(
+                        "motd_network_transmit_bytes",
+                        "Bytes transmitted by the network interface",
+                        interface.tx_bytes as f64,
+                    ),
+                ]
+                .map(|metric| metric.label("interface", &interface.name))
+            })
+            .collect()
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        self.interfaces
+            .iter()
+            .filter(|interface| interface.severity() != Severity::Ok)
+            .map(|interface| {
+                Problem::new(
+                    interface.severity(),
+                    format!("{} is {}", interface.name, interface.state),
+                )
+            })
+            .collect()
+    }
+}
+
+fn read_attribute(interface: &str, name: &str) -> Option<String> {
+    fs::read_to_string(Path::new(NET_PATH).join(interface).join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Interfaces that are not backed by a device live below `/sys/devices/virtual`
+fn is_virtual(interface: &str) -> bool {
+    fs::read_link(Path::new(NET_PATH).join(interface))
+        .map(|target| {
+            target
+                .components()
+                .any(|component| component.as_os_str() == "virtual")
+        })
+        .unwrap_or(false)
+}
+
+/// Address in CIDR notation, or `None` for other kinds of addresses (like MAC addresses)
+fn format_address(addr: &IpAddr, netmask: &IpAddr, show_link_local: bool) -> Option<String> {
+    let (addr, prefix_length) = match (addr, netmask) {
+        (IpAddr::V4(addr), IpAddr::V4(netmask)) => {
+            (net::IpAddr::V4(*addr), u32::from(*netmask).count_ones())
+        }
+        (IpAddr::V6(addr), IpAddr::V6(netmask)) => {
+            let is_link_local = (addr.segments()[0] & 0xffc0) == 0xfe80;
+            if is_link_local && !show_link_local {
+                return None;
+            }
+            (net::IpAddr::V6(*addr), u128::from(*netmask).count_ones())
+        }
+        _ => return None,
+    };
+    Some(format!("{}/{}", addr, prefix_length))
+}
+
+impl Network {
+    pub fn get_output_or_error(self) -> Result<NetworkOutput, NetworkError> {
+        let include = self
+            .interfaces
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let exclude = self
+            .exclude
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let sys = System::new();
+        let mut interfaces = vec![];
+        for (name, network) in sys.networks()? {
+            let is_included =
+                include.is_empty() || include.iter().any(|pattern| pattern.matches(&name));
+            let is_excluded = exclude.iter().any(|pattern| pattern.matches(&name))
+                || (self.hide_loopback
+                    && read_attribute(&name, "type").as_deref() == Some(LOOPBACK_TYPE))
+                || (self.hide_virtual && is_virtual(&name));
+            if !is_included || is_excluded {
+                continue;
+            }
+
+            // Interfaces like the veths of Docker may be gone by now
+            let Ok(stats) = sys.network_stats(&name) else {
+                continue;
+            };
+            let index: u32 = read_attribute(&name, "ifindex")
+                .and_then(|index| index.parse().ok())
+                .unwrap_or(u32::MAX);
+            interfaces.push((
+                index,
+                Interface {
+                    state: read_attribute(&name, "operstate")
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    addresses: network
+                        .addrs
+                        .iter()
+                        .filter_map(|addrs| {
+                            format_address(&addrs.addr, &addrs.netmask, self.show_link_local)
+                        })
+                        .collect(),
+                    rx_bytes: stats.rx_bytes.as_u64(),
+                    tx_bytes: stats.tx_bytes.as_u64(),
+                    selected: !include.is_empty(),
+                    name,
+                },
+            ));
+        }
+
+        if interfaces.is_empty() {
+            return Err(NetworkError::NoInterfaces);
+        }
+        // Same order as `ip link`
+        interfaces.sort_by_key(|(index, _)| *index);
+
+        Ok(NetworkOutput {
+            show_traffic: self.show_traffic,
+            interfaces: interfaces
+                .into_iter()
+                .map(|(_, interface)| interface)
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,235 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::command::{is_installed, BetterCommand, BetterCommandError};
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
+use crate::config::global_config::GlobalConfig;
+use crate::constants::INDENT_WIDTH;
+use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+use crate::severity::{Problem, Severity};
+
+/// Created by the maintainer scripts of packages on Debian and Ubuntu
+const REBOOT_REQUIRED_PATH: &str = "/var/run/reboot-required";
+const REBOOT_REQUIRED_PKGS_PATH: &str = "/var/run/reboot-required.pkgs";
+const MODULES_PATH: &str = "/lib/modules";
+const KERNEL_RELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Check {
+    /// The checks below that apply to the system
+    #[default]
+    Auto,
+    /// `/var/run/reboot-required` and its list of packages
+    Debian,
+    /// `needs-restarting -r` from yum-utils or dnf-utils
+    Rhel,
+    /// Whether the modules of the running kernel are still installed
+    Kernel,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebootRequired {
+    #[serde(default)]
+    check: Check,
+}
+
+#[async_trait]
+impl Component for RebootRequired {
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Reboot required error")
+    }
+    default_prepare!();
+}
+
+#[derive(Error, Debug)]
+pub enum RebootRequiredError {
+    #[error(transparent)]
+    BetterCommand(#[from] BetterCommandError),
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Serialize)]
+pub struct RebootRequiredOutput {
+    required: bool,
+    /// Packages (or the kernel) that were updated since boot, if known
+    triggered_by: Vec<String>,
+}
+
+impl ComponentOutput for RebootRequiredOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(None);
+        let indent = " ".repeat(INDENT_WIDTH);
+
+        if !self.required {
+            section.push(vec![Element::colored("No reboot required", Color::Green)]);
+            return section;
+        }
+
+        section.push(vec![Element::colored(
+            "Reboot required",
+            Severity::Warning.bar_color(),
+        )]);
+        if !self.triggered_by.is_empty() {
+            section.push(vec![Element::plain(format!(
+                "{}by {}",
+                indent,
+                self.triggered_by.join(", ")
+            ))]);
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![Metric::new(
+            "motd_reboot_required",
+            "Whether the system needs to be rebooted",
+            u8::from(self.required),
+        )]
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        match (self.required, self.triggered_by.is_empty()) {
+            (false, _) => vec![],
+            (true, true) => vec![Problem::new(Severity::Warning, "Reboot required")],
+            (true, false) => vec![Problem::new(
+                Severity::Warning,
+                format!("Reboot required by {}", self.triggered_by.join(", ")),
+            )],
+        }
+    }
+}
+
+fn read_optional(path: &str) -> std::io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Packages that asked for a reboot, or `None` if no reboot is required
+fn check_debian() -> std::io::Result<Option<Vec<String>>> {
+    if !Path::new(REBOOT_REQUIRED_PATH).exists() {
+        return Ok(None);
+    }
+    let packages = read_optional(REBOOT_REQUIRED_PKGS_PATH)?.unwrap_or_default();
+    Ok(Some(
+        packages
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+    ))
+}
+
+/// Packages updated since boot that need a reboot, or `None` if no reboot is required
+fn check_rhel() -> Result<Option<Vec<String>>, RebootRequiredError> {
+    let output = BetterCommand::new("needs-restarting").arg("-r").output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Exits with 1 if a reboot is required
+    match output.status.code() {
+        Some(0) => Ok(None),
+        // The packages are listed as `  * kernel`
+        Some(1) => Ok(Some(
+            stdout
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("* "))
+                .map(str::to_string)
+                .collect(),
+        )),
+        exit_code => Err(BetterCommandError::ExitStatusError {
+            executable: "needs-restarting".to_string(),
+            exit_code: exit_code.unwrap_or(-1),
+            error: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Upgrading the kernel on Arch removes the modules of the running one,
+/// so a running kernel without modules has been replaced by a newer one
+fn check_kernel() -> std::io::Result<Option<Vec<String>>> {
+    if !Path::new(MODULES_PATH).is_dir() {
+        return Ok(None);
+    }
+    let running = fs::read_to_string(KERNEL_RELEASE_PATH)?.trim().to_string();
+    if Path::new(MODULES_PATH)
+        .join(&running)
+        .join("modules.dep")
+        .exists()
+    {
+        return Ok(None);
+    }
+
+    let mut installed = fs::read_dir(MODULES_PATH)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|path| path.join("modules.dep").exists())
+        .filter_map(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+        .collect::<Vec<_>>();
+    installed.sort();
+    Ok(Some(vec![format!(
+        "kernel (running {}, installed {})",
+        running,
+        match installed.is_empty() {
+            true => "none".to_string(),
+            false => installed.join(", "),
+        }
+    )]))
+}
+
+impl RebootRequired {
+    pub fn get_output_or_error(self) -> Result<RebootRequiredOutput, RebootRequiredError> {
+        let (debian, rhel, kernel) = match self.check {
+            Check::Auto => {
+                let debian = is_installed("dpkg");
+                let rhel = is_installed("needs-restarting");
+                // Debian and RHEL keep the modules of the running kernel,
+                // so the kernel check is only needed on other distributions
+                (debian, rhel, !debian && !rhel)
+            }
+            check => (
+                check == Check::Debian,
+                check == Check::Rhel,
+                check == Check::Kernel,
+            ),
+        };
+
+        let mut results = vec![];
+        if debian {
+            results.push(check_debian()?);
+        }
+        if rhel {
+            results.push(check_rhel()?);
+        }
+        if kernel {
+            results.push(check_kernel()?);
+        }
+
+        let required = results.iter().any(Option::is_some);
+        let mut triggered_by: Vec<String> = vec![];
+        for package in results.into_iter().flatten().flatten() {
+            // Debian lists a package again for every upgrade since boot
+            if !triggered_by.contains(&package) {
+                triggered_by.push(package);
+            }
+        }
+
+        Ok(RebootRequiredOutput {
+            required,
+            triggered_by,
+        })
+    }
+}
@@ -0,0 +1,472 @@
+use async_trait::async_trait;
+use glob::Pattern;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
+use crate::config::global_config::GlobalConfig;
+use crate::constants::INDENT_WIDTH;
+use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Section};
+use crate::severity::{Problem, Severity};
+
+const HWMON_PATH: &str = "/sys/class/hwmon";
+const THERMAL_PATH: &str = "/sys/class/thermal";
+
+#[derive(Debug, Deserialize)]
+pub struct Sensors {
+    /// Glob patterns of the sensors to show, in the form `chip/input`, all if empty
+    #[serde(default)]
+    include: Vec<String>,
+    /// Glob patterns of sensors to hide
+    #[serde(default)]
+    exclude: Vec<String>,
+    /// Display names of sensors
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    #[serde(default = "default_fans")]
+    fans: bool,
+    /// Temperature above which sensors without a maximum of their own turn yellow
+    max: Option<f64>,
+    /// Temperature above which sensors without a critical temperature of their own turn red
+    crit: Option<f64>,
+}
+
+fn default_fans() -> bool {
+    true
+}
+
+#[async_trait]
+impl Component for Sensors {
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Sensors error")
+    }
+    default_prepare!();
+}
+
+#[derive(Error, Debug)]
+pub enum SensorsError {
+    #[error(transparent)]
+    Pattern(#[from] glob::PatternError),
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reading {
+    Temperature {
+        celsius: f64,
+        max: Option<f64>,
+        crit: Option<f64>,
+    },
+    Fan {
+        rpm: u64,
+        min: Option<u64>,
+    },
+}
+
+impl Reading {
+    fn severity(&self) -> Option<Severity> {
+        match *self {
+            Reading::Temperature { celsius, max, crit } => match (max, crit) {
+                (None, None) => None,
+                _ if crit.is_some_and(|crit| celsius >= crit) => Some(Severity::Critical),
+                _ if max.is_some_and(|max| celsius >= max) => Some(Severity::Warning),
+                _ => Some(Severity::Ok),
+            },
+            Reading::Fan { rpm, min } => min.map(|min| match rpm < min {
+                true => Severity::Warning,
+                false => Severity::Ok,
+            }),
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Reading::Temperature { celsius, .. } => format!("{:.1}°C", celsius),
+            Reading::Fan { rpm, .. } => format!("{} RPM", rpm),
+        }
+    }
+
+    fn limits(&self) -> Vec<String> {
+        match self {
+            Reading::Temperature { max, crit, .. } => [("max", max), ("crit", crit)]
+                .into_iter()
+                .filter_map(|(name, limit)| limit.map(|limit| format!("{} {:.1}°C", name, limit)))
+                .collect(),
+            Reading::Fan { min, .. } => min.iter().map(|min| format!("min {} RPM", min)).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Sensor {
+    /// `chip/input`, used to select sensors in the configuration file
+    id: String,
+    name: String,
+    #[serde(flatten)]
+    reading: Reading,
+}
+
+#[derive(Serialize)]
+pub struct SensorsOutput {
+    sensors: Vec<Sensor>,
+}
+
+impl ComponentOutput for SensorsOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("Sensors"));
+
+        // A machine without sensors, like most virtual machines, is not a problem
+        if self.sensors.is_empty() {
+            section.push(vec![Element::plain(format!(
+                "{}No sensors found",
+                " ".repeat(INDENT_WIDTH)
+            ))]);
+            return section;
+        }
+
+        let name_width = self
+            .sensors
+            .iter()
+            .map(|sensor| sensor.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let value_width = self
+            .sensors
+            .iter()
+            .map(|sensor| sensor.reading.value().chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for sensor in &self.sensors {
+            let value = format!(
+                "{:>value_width$}",
+                sensor.reading.value(),
+                value_width = value_width
+            );
+            let mut line = vec![Element::plain(format!(
+                "{}{:name_width$}  ",
+                " ".repeat(INDENT_WIDTH),
+                sensor.name,
+                name_width = name_width
+            ))];
+            line.push(match sensor.reading.severity() {
+                Some(severity) => Element::colored(value, severity.bar_color()),
+                None => Element::plain(value),
+            });
+            let limits = sensor.reading.limits();
+            if !limits.is_empty() {
+                line.push(Element::plain(format!("  ({})", limits.join(", "))));
+            }
+            section.push(line);
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        self.sensors
+            .iter()
+            .map(|sensor| {
+                match sensor.reading {
+                    Reading::Temperature { celsius, .. } => {
+                        Metric::new("motd_temperature_celsius", "Temperature", celsius)
+                    }
+                    Reading::Fan { rpm, .. } => {
+                        Metric::new("motd_fan_rpm", "Fan speed", rpm as f64)
+                    }
+                }
+                .label("sensor", &sensor.id)
+                .label("name", &sensor.name)
+            })
+            .collect()
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        self.sensors
+            .iter()
+            .filter_map(|sensor| match sensor.reading.severity() {
+                Some(severity) if severity != Severity::Ok => Some(Problem::new(
+                    severity,
+                    format!(
+                        "{} is {} ({})",
+                        sensor.name,
+                        sensor.reading.value(),
+                        sensor.reading.limits().join(", ")
+                    ),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Contents of a sysfs attribute, if it exists and can be read
+fn read_attribute(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Temperatures in sysfs are in millidegrees Celsius
+fn read_celsius(path: &Path) -> Option<f64> {
+    read_attribute(path)?
+        .parse::<f64>()
+        .ok()
+        .map(|millidegrees| millidegrees / 1000.0)
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    read_attribute(path)?.parse().ok()
+}
+
+/// Entries of a directory sorted by the number at the end of their name
+/// (so that `hwmon10` comes after `hwmon9`), or nothing if the directory does not exist
+fn numbered_entries(path: &Path, prefix: &str) -> std::io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+    let mut entries = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|path| {
+            let number = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(prefix)?
+                .parse::<u32>()
+                .ok()?;
+            Some((number, path))
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+    Ok(entries.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Temperature and fan inputs of a hwmon chip, as (input, reading) pairs
+fn read_hwmon_chip(dir: &Path, fans: bool) -> std::io::Result<Vec<(String, Reading)>> {
+    lazy_static! {
+        static ref INPUT_REGEX: Regex = Regex::new(r"^(temp|fan)(\d+)_input$").unwrap();
+    }
+
+    // Older drivers keep their attributes in the directory of the device
+    let attributes_dir = match dir.join("name").exists() {
+        true => dir.to_path_buf(),
+        false => dir.join("device"),
+    };
+
+    let mut inputs = vec![];
+    for entry in fs::read_dir(&attributes_dir)? {
+        let file_name = entry?.file_name();
+        let Some(captures) = file_name
+            .to_str()
+            .and_then(|name| INPUT_REGEX.captures(name))
+        else {
+            continue;
+        };
+        let kind = captures[1].to_string();
+        let number: u32 = captures[2].parse().unwrap_or(0);
+        inputs.push((kind, number));
+    }
+    inputs.sort();
+
+    let mut readings = vec![];
+    for (kind, number) in inputs {
+        let attribute = |name: &str| attributes_dir.join(format!("{}{}_{}", kind, number, name));
+        let input =
+            read_attribute(&attribute("label")).unwrap_or_else(|| format!("{}{}", kind, number));
+        let reading = match kind.as_str() {
+            "temp" => read_celsius(&attribute("input")).map(|celsius| Reading::Temperature {
+                celsius,
+                max: read_celsius(&attribute("max")),
+                crit: read_celsius(&attribute("crit")),
+            }),
+            _ if fans => read_number(&attribute("input")).map(|rpm| Reading::Fan {
+                rpm,
+                min: read_number(&attribute("min")).filter(|min| *min > 0),
+            }),
+            _ => None,
+        };
+        // Inputs of sensors that are not connected cannot be read
+        if let Some(reading) = reading {
+            readings.push((input, reading));
+        }
+    }
+    Ok(readings)
+}
+
+/// Names followed by `-<qualifier>` where they are shared with another item,
+/// like the chips of two NVMe drives that are both named `nvme`
+fn qualify_duplicates(names: Vec<(String, String)>) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (name, _) in &names {
+        *counts.entry(name).or_default() += 1;
+    }
+    names
+        .iter()
+        .map(|(name, qualifier)| match counts[name.as_str()] {
+            1 => name.clone(),
+            _ => format!("{}-{}", name, qualifier),
+        })
+        .collect()
+}
+
+/// Name of the directory of the device behind a hwmon chip, like `nvme0` or `coretemp.1`,
+/// which unlike the number of the chip does not change between boots
+fn hwmon_device(dir: &Path) -> String {
+    fs::canonicalize(dir.join("device"))
+        .unwrap_or_else(|_| dir.to_path_buf())
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn read_hwmon(fans: bool) -> std::io::Result<Vec<(String, Reading)>> {
+    let dirs = numbered_entries(Path::new(HWMON_PATH), "hwmon")?;
+    let chips = dirs
+        .iter()
+        .map(|dir| {
+            let chip = read_attribute(&dir.join("name"))
+                .or_else(|| read_attribute(&dir.join("device/name")))
+                .unwrap_or_else(|| dir.file_name().unwrap().to_string_lossy().to_string());
+            (chip, hwmon_device(dir))
+        })
+        .collect();
+
+    let mut sensors = vec![];
+    for (dir, chip) in dirs.iter().zip(qualify_duplicates(chips)) {
+        // A chip whose attributes cannot be listed should not hide the others
+        let Ok(readings) = read_hwmon_chip(dir, fans) else {
+            continue;
+        };
+        for (input, reading) in readings {
+            sensors.push((format!("{}/{}", chip, input), reading));
+        }
+    }
+    Ok(sensors)
+}
+
+/// Thermal zones, with the temperatures of their hot (or passive) and critical trip points
+fn read_thermal_zones() -> std::io::Result<Vec<(String, Reading)>> {
+    let mut zones = vec![];
+    for dir in numbered_entries(Path::new(THERMAL_PATH), "thermal_zone")? {
+        let Some(celsius) = read_celsius(&dir.join("temp")) else {
+            continue;
+        };
+        let file_name = dir.file_name().unwrap().to_string_lossy().to_string();
+        let zone_type = read_attribute(&dir.join("type")).unwrap_or_else(|| file_name.clone());
+        let number = file_name.trim_start_matches("thermal_zone").to_string();
+        zones.push((dir, celsius, (zone_type, number)));
+    }
+    let names = qualify_duplicates(zones.iter().map(|(_, _, name)| name.clone()).collect());
+
+    let mut sensors = vec![];
+    for ((dir, celsius, _), zone_type) in zones.into_iter().zip(names) {
+        let mut trip_points = HashMap::new();
+        for trip_point in 0.. {
+            let attribute = |name: &str| dir.join(format!("trip_point_{}_{}", trip_point, name));
+            let Some(trip_type) = read_attribute(&attribute("type")) else {
+                break;
+            };
+            if let Some(temperature) = read_celsius(&attribute("temp")) {
+                trip_points.entry(trip_type).or_insert(temperature);
+            }
+        }
+
+        sensors.push((
+            format!("thermal/{}", zone_type),
+            Reading::Temperature {
+                celsius,
+                max: trip_points
+                    .get("hot")
+                    .or_else(|| trip_points.get("passive"))
+                    .copied(),
+                crit: trip_points.get("critical").copied(),
+            },
+        ));
+    }
+    Ok(sensors)
+}
+
+impl Sensors {
+    pub fn get_output_or_error(self) -> Result<SensorsOutput, SensorsError> {
+        let include = self
+            .include
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let exclude = self
+            .exclude
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut sensors = vec![];
+        for (id, reading) in read_hwmon(self.fans)?
+            .into_iter()
+            .chain(read_thermal_zones()?)
+        {
+            if exclude.iter().any(|pattern| pattern.matches(&id)) {
+                continue;
+            }
+            // Sensors are shown in the order of the first pattern they match
+            let position = match include.is_empty() {
+                true => Some(0),
+                false => include.iter().position(|pattern| pattern.matches(&id)),
+            };
+            let Some(position) = position else {
+                continue;
+            };
+
+            let reading = match reading {
+                Reading::Temperature { celsius, max, crit } => Reading::Temperature {
+                    celsius,
+                    max: max.or(self.max),
+                    crit: crit.or(self.crit),
+                },
+                fan => fan,
+            };
+            let name = self.labels.get(&id).cloned().unwrap_or_else(|| id.clone());
+            sensors.push((position, Sensor { id, name, reading }));
+        }
+
+        sensors.sort_by_key(|(position, _)| *position);
+
+        Ok(SensorsOutput {
+            sensors: sensors.into_iter().map(|(_, sensor)| sensor).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_names_are_qualified() {
+        let names = [
+            ("nvme", "nvme0"),
+            ("coretemp", "coretemp.0"),
+            ("nvme", "nvme1"),
+            ("acpitz", "acpitz"),
+        ]
+        .map(|(name, qualifier)| (name.to_string(), qualifier.to_string()));
+        assert_eq!(
+            qualify_duplicates(names.to_vec()),
+            ["nvme-nvme0", "coretemp", "nvme-nvme1", "acpitz"]
+        );
+    }
+}
@@ -1,102 +1,700 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use futures_util::future::{join, join_all};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use termion::{color, style};
 use thiserror::Error;
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::Connection;
 
-use crate::command::{BetterCommand, BetterCommandError};
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::constants::INDENT_WIDTH;
 use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+use crate::severity::{Problem, Severity};
+use crate::systemd::{self, ManagerProxy, UnitStatus};
 
+/// Configuration shared by the system and user services components
+#[derive(Debug, Deserialize)]
+pub struct ServicesConfig {
+    /// Also show all units that are in the failed state
+    #[serde(default)]
+    show_failed: bool,
+    /// Glob patterns of units to show as well, for example `backup-*.service`
+    #[serde(default)]
+    units: Vec<String>,
+    /// Display names (keys) of services (values)
+    // Must come after the other fields, since it takes all remaining keys
+    #[serde(flatten)]
+    services: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
 pub struct ServiceStatus {
-    pub services: HashMap<String, String>,
+    #[serde(flatten)]
+    config: ServicesConfig,
 }
 
+#[derive(Debug, Deserialize)]
 pub struct UserServiceStatus {
-    pub services: HashMap<String, String>,
+    #[serde(flatten)]
+    config: ServicesConfig,
 }
 
 #[async_trait]
 impl Component for ServiceStatus {
-    async fn print(self: Box<Self>, _global_config: &GlobalConfig, _width: Option<usize>) {
-        println!("System Services:");
-        print_or_error(&self.services, false)
-            .unwrap_or_else(|err| println!("Service status error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(
+            get_output_or_error(&self.config, false).await,
+            "Service status error",
+        )
     }
     default_prepare!();
 }
 
 #[async_trait]
 impl Component for UserServiceStatus {
-    async fn print(self: Box<Self>, _global_config: &GlobalConfig, _width: Option<usize>) {
-        println!("User Services:");
-        print_or_error(&self.services, true)
-            .unwrap_or_else(|err| println!("User service status error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(
+            get_output_or_error(&self.config, true).await,
+            "User service status error",
+        )
     }
     default_prepare!();
 }
 
+/// State of one configured service
+#[derive(Serialize)]
+struct Service {
+    name: String,
+    unit: String,
+    status: String,
+    /// More detailed state that depends on the type of unit, for example `running` or `exited`
+    sub_state: String,
+    /// When the unit last changed its state, if it ever did
+    since: Option<DateTime<Local>>,
+    /// When the unit last became active
+    active_since: Option<DateTime<Local>>,
+    main_pid: Option<u32>,
+    memory_bytes: Option<u64>,
+    /// Whether the service was configured by name rather than found by `units` or `show_failed`
+    #[serde(skip)]
+    configured: bool,
+}
+
+impl Service {
+    fn severity(&self) -> Severity {
+        match self.status.as_ref() {
+            // Units found by patterns are often oneshots that are inactive between runs
+            "inactive" | "masked" if self.configured => Severity::Warning,
+            "failed" | "not-found" | "bad-setting" | "error" => Severity::Critical,
+            _ => Severity::Ok,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ServiceStatusOutput {
+    #[serde(skip)]
+    user: bool,
+    #[serde(skip)]
+    show_failed: bool,
+    services: Vec<Service>,
+}
+
+impl ComponentOutput for ServiceStatusOutput {
+    fn to_section(&self, global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let title = match self.user {
+            true => "User Services",
+            false => "System Services",
+        };
+        let mut section = Section::new(Some(title));
+
+        // Only `show_failed` or `units` are configured and nothing failed or matched
+        if self.services.is_empty() {
+            section.push(vec![
+                Element::plain(" ".repeat(INDENT_WIDTH)),
+                match self.show_failed {
+                    true => Element::colored("No failed units", Color::Green),
+                    false => Element::plain("No units matched"),
+                },
+            ]);
+            return section;
+        }
+
+        let padding = self
+            .services
+            .iter()
+            .map(|service| service.name.len())
+            .max()
+            .unwrap_or(0);
+
+        for service in &self.services {
+            let status_color = match (service.status.as_ref(), service.severity()) {
+                ("active", _) => Some(Color::Green),
+                (_, Severity::Warning) => Some(Color::Yellow),
+                (_, Severity::Critical) => Some(Color::Red),
+                _ => None,
+            };
+
+            let mut line = vec![
+                Element::plain(format!(
+                    "{}{}: {}",
+                    " ".repeat(INDENT_WIDTH),
+                    service.name,
+                    " ".repeat(padding - service.name.len()),
+                )),
+                Element::Text {
+                    text: service.status.to_string(),
+                    color: status_color,
+                },
+            ];
+            if !service.sub_state.is_empty() && service.sub_state != service.status {
+                line.push(Element::plain(format!(" ({})", service.sub_state)));
+            }
+            // When a unit stopped is worth knowing, when it started less so
+            if let (Some(since), Severity::Warning | Severity::Critical) =
+                (service.since, service.severity())
+            {
+                line.push(Element::plain(format!(
+                    " since {}",
+                    since.format(&global_config.time_format)
+                )));
+            }
+            section.push(line);
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let scope = match self.user {
+            true => "user",
+            false => "system",
+        };
+        self.services
+            .iter()
+            .flat_map(|service| {
+                let mut metrics = vec![Metric::new(
+                    "motd_service_active",
+                    "Whether the systemd unit is active",
+                    service.status == "active",
+                )];
+                if let Some(memory_bytes) = service.memory_bytes {
+                    metrics.push(Metric::new(
+                        "motd_service_memory_bytes",
+                        "Memory used by the processes of the systemd unit",
+                        memory_bytes as f64,
+                    ));
+                }
+                metrics.into_iter().map(|metric| {
+                    metric
+                        .label("name", &service.name)
+                        .label("unit", &service.unit)
+                        .label("scope", scope)
+                })
+            })
+            .collect()
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        self.services
+            .iter()
+            .filter(|service| service.severity() != Severity::Ok)
+            .map(|service| {
+                Problem::new(
+                    service.severity(),
+                    format!("{} is {}", service.name, service.status),
+                )
+            })
+            .collect()
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ServiceStatusError {
     #[error("Empty configuration for system services. Please remove the entire block to disable this component.")]
     ConfigEmpty,
 
-    #[error(transparent)]
-    BetterCommand(#[from] BetterCommandError),
+    #[error("Could not connect to systemd on the {bus} bus: {source}")]
+    Connect {
+        bus: &'static str,
+        source: zbus::Error,
+    },
 
     #[error(transparent)]
-    IO(#[from] std::io::Error),
+    DBus(#[from] zbus::Error),
+}
+
+/// Types of units that systemd knows, by the suffix of their names
+const UNIT_SUFFIXES: [&str; 11] = [
+    "service",
+    "socket",
+    "device",
+    "mount",
+    "automount",
+    "swap",
+    "target",
+    "path",
+    "timer",
+    "slice",
+    "scope",
+];
+
+/// Name of a configured unit as systemd expects it, where `cron` means `cron.service` as with `systemctl`
+fn unit_name(unit: &str) -> String {
+    match unit.rsplit_once('.') {
+        Some((_, suffix)) if UNIT_SUFFIXES.contains(&suffix) => unit.to_string(),
+        _ => format!("{}.service", unit),
+    }
+}
+
+/// All properties of one interface of a unit, or none if they cannot be read
+async fn get_properties(
+    connection: &Connection,
+    path: &OwnedObjectPath,
+    interface: &'static str,
+) -> HashMap<String, OwnedValue> {
+    let properties = async {
+        PropertiesProxy::builder(connection)
+            .destination(systemd::BUS_NAME)?
+            .path(path)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?
+            .get_all(Some(InterfaceName::from_static_str_unchecked(interface)).into())
+            .await
+            .map_err(zbus::Error::from)
+    };
+    properties.await.unwrap_or_default()
 }
 
-fn get_service_status(service: &str, user: bool) -> Result<String, ServiceStatusError> {
-    let executable = "systemctl";
-    let mut args = vec!["is-active", service];
+fn property<T>(properties: &HashMap<String, OwnedValue>, name: &str) -> Option<T>
+where
+    T: TryFrom<OwnedValue>,
+{
+    properties.get(name)?.try_clone().ok()?.try_into().ok()
+}
+
+/// A listed unit with the properties that are not part of the list
+struct UnitDetails {
+    status: UnitStatus,
+    /// The name of the unit and its aliases
+    names: Vec<String>,
+    unit: HashMap<String, OwnedValue>,
+    service: HashMap<String, OwnedValue>,
+}
 
-    if user {
-        args.insert(0, "--user");
+async fn get_details(connection: &Connection, status: UnitStatus) -> UnitDetails {
+    let service = async {
+        match status.name.ends_with(".service") {
+            true => get_properties(connection, &status.path, systemd::SERVICE_INTERFACE).await,
+            false => HashMap::new(),
+        }
+    };
+    let (unit, service) = join(
+        get_properties(connection, &status.path, systemd::UNIT_INTERFACE),
+        service,
+    )
+    .await;
+    UnitDetails {
+        names: property(&unit, "Names").unwrap_or_default(),
+        status,
+        unit,
+        service,
     }
+}
 
-    let output = BetterCommand::new(executable)
-        .args(args)
-        .get_output_string()?;
+impl UnitDetails {
+    fn service(&self, name: String, configured: bool) -> Service {
+        let status = &self.status;
+        let active_since = match status.active_state.as_str() {
+            "active" | "reloading" => property(&self.unit, "ActiveEnterTimestamp"),
+            _ => None,
+        };
+        Service {
+            name,
+            unit: status.name.clone(),
+            // Units that do not exist or are masked are shown as such instead of inactive
+            status: match status.load_state.as_str() {
+                "loaded" => status.active_state.clone(),
+                _ => status.load_state.clone(),
+            },
+            sub_state: status.sub_state.clone(),
+            since: property(&self.unit, "StateChangeTimestamp").and_then(systemd::timestamp),
+            active_since: active_since.and_then(systemd::timestamp),
+            main_pid: property(&self.service, "MainPID").filter(|pid| *pid != 0),
+            // Without memory accounting the memory is not known
+            memory_bytes: property(&self.service, "MemoryCurrent")
+                .filter(|memory| *memory != u64::MAX),
+            configured,
+        }
+    }
+}
 
-    Ok(output.split_whitespace().collect())
+/// A configured unit that systemd did not list, because its name is invalid
+fn not_found(name: String, unit: String) -> Service {
+    Service {
+        name,
+        unit,
+        status: "not-found".to_string(),
+        sub_state: String::new(),
+        since: None,
+        active_since: None,
+        main_pid: None,
+        memory_bytes: None,
+        configured: true,
+    }
 }
 
-pub fn print_or_error(
-    config: &HashMap<String, String>,
+pub async fn get_output_or_error(
+    config: &ServicesConfig,
     user: bool,
-) -> Result<(), ServiceStatusError> {
-    if config.is_empty() {
+) -> Result<ServiceStatusOutput, ServiceStatusError> {
+    if config.services.is_empty() && !config.show_failed && config.units.is_empty() {
         return Err(ServiceStatusError::ConfigEmpty);
     }
 
-    let padding = config.keys().map(|x| x.len()).max().unwrap();
+    let connection =
+        systemd::connect(user)
+            .await
+            .map_err(|source| ServiceStatusError::Connect {
+                bus: systemd::bus_name(user),
+                source,
+            })?;
+    Ok(ServiceStatusOutput {
+        user,
+        show_failed: config.show_failed,
+        services: get_services(&connection, config).await?,
+    })
+}
 
-    for key in config.keys().sorted() {
-        let status = get_service_status(config.get(key).unwrap(), user)?;
+async fn get_services(
+    connection: &Connection,
+    config: &ServicesConfig,
+) -> Result<Vec<Service>, ServiceStatusError> {
+    let manager = ManagerProxy::new(connection).await?;
 
-        let status_color = match status.as_ref() {
-            "active" => color::Fg(color::Green).to_string(),
-            "inactive" => color::Fg(color::Yellow).to_string(),
-            "failed" => color::Fg(color::Red).to_string(),
-            _ => style::Reset.to_string(),
-        };
+    // The states of all configured services are fetched at once, and matched by name
+    // since systemd skips invalid names and lists aliases under the name of their unit
+    let names: Vec<&String> = config.services.keys().sorted().collect();
+    let units: Vec<String> = names
+        .iter()
+        .map(|name| unit_name(&config.services[*name]))
+        .collect();
+    let listed = match units.is_empty() {
+        true => vec![],
+        false => {
+            let units: Vec<&str> = units.iter().map(String::as_str).collect();
+            manager.list_units_by_names(&units).await?
+        }
+    };
+    let listed = join_all(
+        listed
+            .into_iter()
+            .map(|status| get_details(connection, status)),
+    )
+    .await;
+    let mut services: Vec<Service> = names
+        .into_iter()
+        .zip(units)
+        .map(|(name, unit)| {
+            match listed
+                .iter()
+                .find(|details| details.status.name == unit || details.names.contains(&unit))
+            {
+                Some(details) => details.service(name.clone(), true),
+                None => not_found(name.clone(), unit),
+            }
+        })
+        .collect();
 
-        println!(
-            "{}{}: {}{}{}{}",
-            " ".repeat(INDENT_WIDTH),
-            key,
-            " ".repeat(padding - key.len()),
-            status_color,
-            status,
-            style::Reset,
+    // Then the other failed or matching units
+    let mut other_units = vec![];
+    if config.show_failed {
+        other_units.extend(manager.list_units_by_patterns(&["failed"], &[]).await?);
+    }
+    if !config.units.is_empty() {
+        let patterns: Vec<&str> = config.units.iter().map(String::as_str).collect();
+        other_units.extend(manager.list_units_by_patterns(&[], &patterns).await?);
+    }
+    other_units.sort_by(|a, b| a.name.cmp(&b.name));
+    other_units.dedup_by(|a, b| a.name == b.name);
+    other_units.retain(|status| !services.iter().any(|service| service.unit == status.name));
+    let other_units = join_all(
+        other_units
+            .into_iter()
+            .map(|status| get_details(connection, status)),
+    )
+    .await;
+    services.extend(
+        other_units
+            .into_iter()
+            .map(|details| details.service(details.status.name.clone(), false)),
+    );
+
+    Ok(services)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use zbus::connection::Builder;
+    use zbus::interface;
+
+    type UnitTuple = (
+        String,
+        String,
+        String,
+        String,
+        String,
+        String,
+        OwnedObjectPath,
+        u32,
+        String,
+        OwnedObjectPath,
+    );
+
+    fn unit_path(unit: &str) -> OwnedObjectPath {
+        let escaped = unit.replace('.', "_2e").replace('-', "_2d");
+        OwnedObjectPath::try_from(format!("/org/freedesktop/systemd1/unit/{}", escaped)).unwrap()
+    }
+
+    /// Lists units like systemd: invalid names are skipped,
+    /// aliases are listed under the name of their unit and unknown units are not found
+    struct FakeManager;
+
+    #[interface(name = "org.freedesktop.systemd1.Manager")]
+    impl FakeManager {
+        fn list_units_by_names(&self, names: Vec<String>) -> Vec<UnitTuple> {
+            names
+                .iter()
+                .filter(|name| !name.contains('/'))
+                .map(|name| match name.as_str() {
+                    "cron.service" => unit_tuple("cron.service", "loaded", "active", "running"),
+                    "sshd.service" => unit_tuple("ssh.service", "loaded", "failed", "failed"),
+                    "backup.timer" => unit_tuple("backup.timer", "loaded", "inactive", "dead"),
+                    unit => unit_tuple(unit, "not-found", "inactive", "dead"),
+                })
+                .collect()
+        }
+
+        /// A failed unit, and an inactive oneshot for any pattern
+        fn list_units_by_patterns(
+            &self,
+            states: Vec<String>,
+            patterns: Vec<String>,
+        ) -> Vec<UnitTuple> {
+            match (states.is_empty(), patterns.is_empty()) {
+                (false, true) => vec![unit_tuple("ssh.service", "loaded", "failed", "failed")],
+                (true, false) => vec![
+                    unit_tuple("backup-db.service", "loaded", "inactive", "dead"),
+                    unit_tuple("ssh.service", "loaded", "failed", "failed"),
+                ],
+                _ => vec![],
+            }
+        }
+    }
+
+    fn unit_tuple(unit: &str, load_state: &str, active_state: &str, sub_state: &str) -> UnitTuple {
+        (
+            unit.to_string(),
+            String::new(),
+            load_state.to_string(),
+            active_state.to_string(),
+            sub_state.to_string(),
+            String::new(),
+            unit_path(unit),
+            0,
+            String::new(),
+            OwnedObjectPath::try_from("/").unwrap(),
+        )
+    }
+
+    struct FakeUnit {
+        names: Vec<String>,
+    }
+
+    #[interface(name = "org.freedesktop.systemd1.Unit")]
+    impl FakeUnit {
+        #[zbus(property)]
+        fn names(&self) -> Vec<String> {
+            self.names.clone()
+        }
+
+        #[zbus(property)]
+        fn state_change_timestamp(&self) -> u64 {
+            1_700_000_000_000_000
+        }
+
+        #[zbus(property)]
+        fn active_enter_timestamp(&self) -> u64 {
+            1_600_000_000_000_000
+        }
+    }
+
+    struct FakeService;
+
+    #[interface(name = "org.freedesktop.systemd1.Service")]
+    impl FakeService {
+        #[zbus(property, name = "MainPID")]
+        fn main_pid(&self) -> u32 {
+            42
+        }
+
+        #[zbus(property)]
+        fn memory_current(&self) -> u64 {
+            u64::MAX
+        }
+    }
+
+    /// A private bus, stopped when dropped
+    struct DBusDaemon(Child);
+
+    impl Drop for DBusDaemon {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    fn start_bus() -> (DBusDaemon, String) {
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--nopidfile", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon is needed for this test");
+        let mut address = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        (DBusDaemon(child), address.trim().to_string())
+    }
+
+    /// A private bus with a fake systemd, and the connections of systemd and of a client
+    async fn fake_systemd() -> (DBusDaemon, Connection, Connection) {
+        let (daemon, address) = start_bus();
+        let systemd = Builder::address(address.as_str())
+            .unwrap()
+            .name(systemd::BUS_NAME)
+            .unwrap()
+            .serve_at("/org/freedesktop/systemd1", FakeManager)
+            .unwrap()
+            .serve_at(
+                unit_path("cron.service"),
+                FakeUnit {
+                    names: vec!["cron.service".to_string()],
+                },
+            )
+            .unwrap()
+            .serve_at(unit_path("cron.service"), FakeService)
+            .unwrap()
+            .serve_at(
+                unit_path("ssh.service"),
+                FakeUnit {
+                    names: vec!["ssh.service".to_string(), "sshd.service".to_string()],
+                },
+            )
+            .unwrap()
+            .serve_at(
+                unit_path("backup-db.service"),
+                FakeUnit {
+                    names: vec!["backup-db.service".to_string()],
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let connection = Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        (daemon, systemd, connection)
+    }
+
+    #[tokio::test]
+    async fn configured_services_are_matched_by_name() {
+        let (_daemon, _systemd, connection) = fake_systemd().await;
+        let config: ServicesConfig = toml::from_str(
+            r#"
+            Backup = "backup.timer"
+            Cron = "cron"
+            Invalid = "bad/name"
+            Missing = "missing"
+            SSH = "sshd"
+            "#,
+        )
+        .unwrap();
+        let services = get_services(&connection, &config).await.unwrap();
+
+        let states: Vec<(&str, &str, &str)> = services
+            .iter()
+            .map(|service| {
+                (
+                    service.name.as_str(),
+                    service.unit.as_str(),
+                    service.status.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            states,
+            [
+                ("Backup", "backup.timer", "inactive"),
+                ("Cron", "cron.service", "active"),
+                ("Invalid", "bad/name.service", "not-found"),
+                ("Missing", "missing.service", "not-found"),
+                ("SSH", "ssh.service", "failed"),
+            ]
+        );
+
+        let cron = &services[1];
+        assert_eq!(cron.main_pid, Some(42));
+        assert_eq!(cron.memory_bytes, None);
+        assert_eq!(
+            cron.active_since.map(|time| time.timestamp()),
+            Some(1_600_000_000)
+        );
+        assert_eq!(
+            services[4].since.map(|time| time.timestamp()),
+            Some(1_700_000_000)
         );
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn units_found_by_patterns_are_only_problems_when_failed() {
+        let (_daemon, _systemd, connection) = fake_systemd().await;
+        let config: ServicesConfig =
+            toml::from_str("show_failed = true\nunits = [\"backup-*.service\"]").unwrap();
+        let output = ServiceStatusOutput {
+            user: false,
+            show_failed: config.show_failed,
+            services: get_services(&connection, &config).await.unwrap(),
+        };
+
+        let states: Vec<(&str, Severity)> = output
+            .services
+            .iter()
+            .map(|service| (service.name.as_str(), service.severity()))
+            .collect();
+        assert_eq!(
+            states,
+            [
+                ("backup-db.service", Severity::Ok),
+                ("ssh.service", Severity::Critical)
+            ]
+        );
+        assert_eq!(output.problems().len(), 1);
+    }
 }
@@ -0,0 +1,430 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use thiserror::Error;
+
+use crate::command::{BetterCommand, BetterCommandError};
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
+use crate::components::last_login::{current_user, previous_login};
+use crate::config::global_config::GlobalConfig;
+use crate::constants::INDENT_WIDTH;
+use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+
+/// Logs of sshd on Debian and on Red Hat, read before falling back to the journal
+const LOG_FILES: [&str; 2] = ["/var/log/auth.log", "/var/log/secure"];
+
+#[derive(Debug, Deserialize)]
+pub struct SshFailures {
+    /// How far back to count failures, like `24h`, instead of since the previous login
+    window: Option<String>,
+    /// User whose previous login starts the window, the user running rust-motd by default
+    user: Option<String>,
+    /// Log file of sshd to read instead of finding one or reading the journal
+    log_file: Option<PathBuf>,
+    /// Number of source IPs and usernames to list
+    #[serde(default = "default_top")]
+    top: usize,
+}
+
+fn default_top() -> usize {
+    5
+}
+
+#[async_trait]
+impl Component for SshFailures {
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "SSH failures error")
+    }
+    default_prepare!();
+}
+
+#[derive(Error, Debug)]
+pub enum SshFailuresError {
+    #[error("Invalid window: {0}")]
+    Window(#[from] humantime::DurationError),
+
+    #[error(transparent)]
+    BetterCommand(#[from] BetterCommandError),
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+/// A value and how many failures it took part in
+#[derive(Serialize)]
+struct Count {
+    value: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+pub struct SshFailuresOutput {
+    since: DateTime<Local>,
+    /// Whether `since` is the previous login of the user rather than the configured window
+    since_login: bool,
+    /// Where the failures were read from, a log file or `journal`
+    source: String,
+    failures: usize,
+    sources: Vec<Count>,
+    users: Vec<Count>,
+}
+
+impl ComponentOutput for SshFailuresOutput {
+    fn to_section(&self, global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("SSH Failures"));
+        let indent = " ".repeat(INDENT_WIDTH);
+
+        let summary = format!(
+            "{} failed login attempt{} since {}{}",
+            self.failures,
+            if self.failures == 1 { "" } else { "s" },
+            if self.since_login {
+                "the last login at "
+            } else {
+                ""
+            },
+            self.since.format(&global_config.time_format)
+        );
+        section.push(vec![
+            Element::plain(&indent),
+            match self.failures {
+                0 => Element::colored(summary, Color::Green),
+                _ => Element::colored(summary, Color::Yellow),
+            },
+        ]);
+
+        for (title, counts) in [("Top sources", &self.sources), ("Top users", &self.users)] {
+            if counts.is_empty() {
+                continue;
+            }
+            section.push(vec![Element::plain(format!("{indent}{title}:"))]);
+            let padding = counts
+                .iter()
+                .map(|count| count.value.len())
+                .max()
+                .unwrap_or(0);
+            for count in counts {
+                section.push(vec![Element::plain(format!(
+                    "{indent}{indent}{}{} {}",
+                    count.value,
+                    " ".repeat(padding - count.value.len()),
+                    count.count
+                ))]);
+            }
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            Metric::new(
+                "motd_ssh_failures",
+                "Number of failed SSH login attempts in the window",
+                self.failures as f64,
+            ),
+            Metric::new(
+                "motd_ssh_failures_since_timestamp",
+                "Start of the window of failed SSH login attempts",
+                self.since.timestamp() as f64,
+            ),
+        ]
+    }
+}
+
+/// A failed authentication, from a line like
+/// `Failed password for invalid user admin from 192.0.2.1 port 4242 ssh2`,
+/// times the number of repetitions that syslog may have folded into it
+struct Failure {
+    user: String,
+    source: String,
+    repeated: usize,
+}
+
+fn parse_message(message: &str) -> Option<Failure> {
+    lazy_static! {
+        static ref FAILURE_REGEX: Regex = Regex::new(
+            r"^(?:message repeated (\d+) times: \[ )?Failed \S+ for (?:invalid user )?(.*) from (\S+) port \d+"
+        )
+        .unwrap();
+    }
+
+    let captures = FAILURE_REGEX.captures(message)?;
+    Some(Failure {
+        user: captures[2].to_string(),
+        source: captures[3].to_string(),
+        repeated: captures
+            .get(1)
+            .and_then(|repeated| repeated.as_str().parse().ok())
+            .unwrap_or(1),
+    })
+}
+
+/// Time of a syslog line, either RFC 3339 or the traditional `Oct 18 09:15:02` without a year
+fn parse_time(line: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Some(time) = line
+        .split_whitespace()
+        .next()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+    {
+        return Some(time.with_timezone(&Local));
+    }
+
+    let time = format!("{} {}", now.year(), line.get(..15)?);
+    let time = NaiveDateTime::parse_from_str(&time, "%Y %b %e %H:%M:%S").ok()?;
+    let time = Local.from_local_datetime(&time).earliest()?;
+    // Lines from December read in January
+    match time > now + Duration::days(1) {
+        true => time.with_year(now.year() - 1),
+        false => Some(time),
+    }
+}
+
+/// Failures logged by sshd to a syslog file and its most recent rotation, since `since`
+fn read_log_file(path: &Path, since: DateTime<Local>) -> Result<Vec<Failure>, SshFailuresError> {
+    lazy_static! {
+        // sshd-session logs the authentication since OpenSSH 9.8
+        static ref SSHD_REGEX: Regex = Regex::new(r" sshd(?:-session)?\[\d+\]: (.*)").unwrap();
+    }
+
+    let now = Local::now();
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    // Attempted usernames are not necessarily valid UTF-8
+    let mut contents = fs::read(&rotated).unwrap_or_default();
+    contents.extend(fs::read(path)?);
+    let contents = String::from_utf8_lossy(&contents);
+
+    Ok(contents
+        .lines()
+        .filter(|line| parse_time(line, now).is_some_and(|time| time >= since))
+        .filter_map(|line| SSHD_REGEX.captures(line))
+        .filter_map(|captures| parse_message(&captures[1]))
+        .collect())
+}
+
+/// Failures logged by sshd to the journal since `since`
+fn read_journal(since: DateTime<Local>) -> Result<Vec<Failure>, SshFailuresError> {
+    let output = BetterCommand::new("journalctl")
+        .args(["--quiet", "--no-pager", "--output=cat"])
+        .arg(format!("--since=@{}", since.timestamp()))
+        .args(["_COMM=sshd", "_COMM=sshd-session"])
+        .check_status_and_get_output_string()?;
+    Ok(output.lines().filter_map(parse_message).collect())
+}
+
+/// The `top` most frequent values, most frequent first
+fn top_counts<'a>(values: impl Iterator<Item = (&'a str, usize)>, top: usize) -> Vec<Count> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (value, count) in values {
+        *counts.entry(value).or_default() += count;
+    }
+    let mut counts: Vec<Count> = counts
+        .into_iter()
+        .map(|(value, count)| Count {
+            value: value.to_string(),
+            count,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts.truncate(top);
+    counts
+}
+
+impl SshFailures {
+    /// Start of the configured window, otherwise the previous login of the user,
+    /// otherwise the last day
+    fn since(&self) -> Result<(DateTime<Local>, bool), SshFailuresError> {
+        if let Some(window) = &self.window {
+            let window = humantime::parse_duration(window)?;
+            let since = Duration::from_std(window)
+                .ok()
+                .and_then(|window| Local::now().checked_sub_signed(window))
+                .unwrap_or_else(|| UNIX_EPOCH.into());
+            return Ok((since, false));
+        }
+
+        let user = match &self.user {
+            Some(user) => user.clone(),
+            None => current_user()?,
+        };
+        // Without a readable wtmp there is no previous login to count from
+        match previous_login(&user).ok().flatten() {
+            Some(login) => Ok((login, true)),
+            None => Ok((Local::now() - Duration::days(1), false)),
+        }
+    }
+
+    pub fn get_output_or_error(self) -> Result<SshFailuresOutput, SshFailuresError> {
+        let (since, since_login) = self.since()?;
+
+        let log_file = self.log_file.clone().or_else(|| {
+            LOG_FILES
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())
+        });
+        let (failures, source) = match log_file {
+            Some(path) => (read_log_file(&path, since)?, path.display().to_string()),
+            None => (read_journal(since)?, "journal".to_string()),
+        };
+
+        Ok(SshFailuresOutput {
+            since,
+            since_login,
+            source,
+            failures: failures.iter().map(|failure| failure.repeated).sum(),
+            sources: top_counts(
+                failures
+                    .iter()
+                    .map(|failure| (failure.source.as_str(), failure.repeated)),
+                self.top,
+            ),
+            users: top_counts(
+                failures
+                    .iter()
+                    .map(|failure| (failure.user.as_str(), failure.repeated)),
+                self.top,
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(time: &str) -> DateTime<Local> {
+        let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local.from_local_datetime(&time).unwrap()
+    }
+
+    fn summary(failure: &Failure) -> (&str, &str, usize) {
+        (&failure.user, &failure.source, failure.repeated)
+    }
+
+    #[test]
+    fn messages() {
+        for (message, expected) in [
+            (
+                "Failed password for root from 192.0.2.1 port 4242 ssh2",
+                Some(("root", "192.0.2.1", 1)),
+            ),
+            (
+                "Failed password for invalid user admin from 2001:db8::1 port 4242 ssh2",
+                Some(("admin", "2001:db8::1", 1)),
+            ),
+            (
+                "Failed publickey for git from 198.51.100.23 port 50022 ssh2: RSA SHA256:abc",
+                Some(("git", "198.51.100.23", 1)),
+            ),
+            (
+                "message repeated 3 times: [ Failed password for root from 192.0.2.1 port 4242 ssh2]",
+                Some(("root", "192.0.2.1", 3)),
+            ),
+            (
+                "Accepted publickey for alice from 192.0.2.9 port 4242 ssh2: ED25519 SHA256:abc",
+                None,
+            ),
+            ("Connection closed by 192.0.2.1 port 4242 [preauth]", None),
+        ] {
+            let failure = parse_message(message);
+            assert_eq!(failure.as_ref().map(summary), expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn times() {
+        let now = local("2026-10-18 12:00:00");
+        assert_eq!(
+            parse_time(
+                "2026-10-18T09:15:02.123456+00:00 host sshd-session[1]: Connection closed",
+                now
+            ),
+            Some(
+                DateTime::parse_from_rfc3339("2026-10-18T09:15:02.123456+00:00")
+                    .unwrap()
+                    .with_timezone(&Local)
+            )
+        );
+        assert_eq!(
+            parse_time("Oct 18 09:15:02 host sshd[1]: Connection closed", now),
+            Some(local("2026-10-18 09:15:02"))
+        );
+        assert_eq!(
+            parse_time("Oct  8 09:15:02 host sshd[1]: Connection closed", now),
+            Some(local("2026-10-08 09:15:02"))
+        );
+        assert_eq!(parse_time("host sshd[1]: Connection closed", now), None);
+        assert_eq!(parse_time("", now), None);
+    }
+
+    #[test]
+    fn times_across_new_year() {
+        let now = local("2027-01-02 12:00:00");
+        assert_eq!(
+            parse_time("Dec 31 23:59:01 host sshd[1]: Connection closed", now),
+            Some(local("2026-12-31 23:59:01"))
+        );
+        assert_eq!(
+            parse_time("Jan  2 08:00:00 host sshd[1]: Connection closed", now),
+            Some(local("2027-01-02 08:00:00"))
+        );
+        // Clocks of other hosts may be slightly ahead
+        assert_eq!(
+            parse_time("Jan  3 08:00:00 host sshd[1]: Connection closed", now),
+            Some(local("2027-01-03 08:00:00"))
+        );
+    }
+
+    #[test]
+    fn log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auth.log");
+        fs::write(
+            dir.path().join("auth.log.1"),
+            "1999-06-01T12:00:00+00:00 host sshd[100]: Failed password for root from 203.0.113.7 port 22 ssh2\n\
+             2026-10-17T23:00:00.5+00:00 host sshd[100]: Failed password for root from 192.0.2.1 port 4242 ssh2\n",
+        )
+        .unwrap();
+        fs::write(
+            &path,
+            "Jan  1 00:00:01 host sshd-session[200]: Failed password for invalid user admin from 198.51.100.23 port 5555 ssh2\n\
+             Jan  1 00:00:02 host sshd-session[200]: message repeated 3 times: [ Failed password for invalid user admin from 198.51.100.23 port 5555 ssh2]\n\
+             Jan  1 00:00:03 host sshd[300]: Accepted publickey for alice from 192.0.2.9 port 4242 ssh2\n\
+             Jan  1 00:00:04 host sudo[400]: Failed password for root from 192.0.2.1 port 4242 ssh2\n",
+        )
+        .unwrap();
+
+        let since = local("2000-01-01 00:00:00");
+        let failures = read_log_file(&path, since).unwrap();
+        let failures: Vec<_> = failures.iter().map(summary).collect();
+        assert_eq!(
+            failures,
+            [
+                ("root", "192.0.2.1", 1),
+                ("admin", "198.51.100.23", 1),
+                ("admin", "198.51.100.23", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts() {
+        let values = [("b", 1), ("a", 1), ("c", 3), ("b", 1), ("d", 1)];
+        let counts: Vec<(String, usize)> = top_counts(values.into_iter(), 3)
+            .into_iter()
+            .map(|count| (count.value, count.count))
+            .collect();
+        assert_eq!(counts, [("c".into(), 3), ("b".into(), 2), ("a".into(), 1)]);
+        assert!(top_counts(values.into_iter(), 0).is_empty());
+    }
+}
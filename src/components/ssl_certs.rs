@@ -1,17 +1,28 @@
 use async_trait::async_trait;
-use chrono::{Duration, TimeZone, Utc};
-use openssl::x509::X509;
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, Read};
-use termion::{color, style};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use itertools::Itertools;
+use openssl::error::ErrorStack;
+use openssl::pkcs12::Pkcs12;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use openssl::stack::Stack;
+use openssl::x509::store::{X509Store, X509StoreBuilder, X509StoreRef};
+use openssl::x509::{X509Ref, X509StoreContext, X509VerifyResult, X509};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration as StdDuration;
 use thiserror::Error;
 
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::constants::INDENT_WIDTH;
 use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+use crate::severity::{Problem, Severity};
 
 #[derive(Debug, Deserialize, Default)]
 enum SortMethod {
@@ -24,19 +35,80 @@ enum SortMethod {
     Manual,
 }
 
+const DEFAULT_WARNING_DAYS: i64 = 30;
+const DEFAULT_HTTPS_PORT: u16 = 443;
+const CONNECT_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+
+/// Extensions of the files that are inspected when a directory is given
+const CERT_EXTENSIONS: [&str; 6] = ["pem", "crt", "cer", "der", "p12", "pfx"];
+
+/// Bundles of trusted certificates of common distributions,
+/// since the vendored OpenSSL does not know where the system keeps them
+const TRUST_STORE_FILES: [&str; 4] = [
+    "/etc/ssl/certs/ca-certificates.crt", // Debian, Ubuntu, Arch
+    "/etc/pki/tls/certs/ca-bundle.crt",   // Fedora, RHEL
+    "/etc/ssl/ca-bundle.pem",             // openSUSE
+    "/etc/ssl/cert.pem",                  // Alpine
+];
+
+/// `X509_V_ERR_CERT_HAS_EXPIRED`, which is reported as the status of the certificate instead
+const CERT_HAS_EXPIRED: i32 = 10;
+
+fn default_warning_days() -> i64 {
+    DEFAULT_WARNING_DAYS
+}
+
+fn default_verify_chain() -> bool {
+    true
+}
+
+/// A certificate in the configuration file, either just its path or a table with
+/// the path and the password of a PKCS#12 file
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CertConfig {
+    Path(String),
+    Detailed {
+        path: String,
+        #[serde(default)]
+        password: String,
+    },
+}
+
+impl CertConfig {
+    fn path_and_password(&self) -> (&str, &str) {
+        match self {
+            CertConfig::Path(path) => (path, ""),
+            CertConfig::Detailed { path, password } => (path, password),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SSLCerts {
     #[serde(default)]
     sort_method: SortMethod,
-    certs: HashMap<String, String>,
+    /// Certificates expiring within this many days are shown as expiring
+    #[serde(default = "default_warning_days")]
+    warning_days: i64,
+    /// Check that the certificates chain up to the trust store of the system
+    #[serde(default = "default_verify_chain")]
+    verify_chain: bool,
+    /// Show the subject and alternative names of the certificates
+    #[serde(default)]
+    show_details: bool,
+    /// Files, directories or glob patterns of certificates
+    #[serde(default)]
+    certs: HashMap<String, CertConfig>,
+    /// `host:port` of TLS servers whose served certificates are checked
+    #[serde(default)]
+    endpoints: HashMap<String, String>,
 }
 
 #[async_trait]
 impl Component for SSLCerts {
-    async fn print(self: Box<Self>, global_config: &GlobalConfig, _width: Option<usize>) {
-        self.print_or_error(global_config)
-            .unwrap_or_else(|err| println!("SSL Certificate error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "SSL Certificate error")
     }
     default_prepare!();
 }
@@ -50,64 +122,687 @@ pub enum SSLCertsError {
     IO(#[from] std::io::Error),
 
     #[error(transparent)]
-    ErrorStack(#[from] openssl::error::ErrorStack),
+    ErrorStack(#[from] ErrorStack),
+
+    #[error(transparent)]
+    Pattern(#[from] glob::PatternError),
+
+    #[error("Could not read certificates from {}: {source}", path.display())]
+    Load {
+        path: PathBuf,
+        source: Box<SSLCertsError>,
+    },
+
+    #[error("No certificates found in {path}")]
+    NoCertificateFound { path: String },
+
+    #[error("Invalid endpoint {endpoint:?}, expected host:port")]
+    InvalidEndpoint { endpoint: String },
+
+    #[error("Could not connect to {endpoint}: {source}")]
+    Connect {
+        endpoint: String,
+        source: std::io::Error,
+    },
+
+    #[error("TLS handshake with {endpoint} failed: {message}")]
+    Handshake { endpoint: String, message: String },
+
+    #[error("{endpoint} did not send a certificate")]
+    NoCertificate { endpoint: String },
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CertStatus {
+    Expired,
+    Expiring,
+    Valid,
+}
+
+#[derive(Serialize)]
 struct CertInfo {
     name: String,
-    status: String,
-    expiration: systemstat::DateTime<systemstat::Utc>,
+    status: CertStatus,
+    /// Earliest expiration of all certificates in the file or chain
+    expiration: DateTime<Utc>,
+    subject: String,
+    alt_names: Vec<String>,
+    /// Why the chain does not verify, if it was checked
+    verification_error: Option<String>,
+}
+
+/// A file or endpoint whose certificates could not be checked
+#[derive(Serialize)]
+struct CheckFailure {
+    name: String,
+    error: String,
+}
+
+#[derive(Serialize)]
+pub struct SSLCertsOutput {
+    #[serde(skip)]
+    show_details: bool,
+    certs: Vec<CertInfo>,
+    failures: Vec<CheckFailure>,
+}
+
+impl ComponentOutput for SSLCertsOutput {
+    fn to_section(&self, global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("SSL Certificates"));
+
+        for cert_info in &self.certs {
+            let (status, color) = match cert_info.status {
+                CertStatus::Expired => ("expired on", Color::Red),
+                CertStatus::Expiring => ("expiring on", Color::Yellow),
+                CertStatus::Valid => ("valid until", Color::Green),
+            };
+            let mut line = vec![
+                Element::plain(format!("{}{} ", " ".repeat(INDENT_WIDTH), cert_info.name)),
+                Element::colored(status, color),
+                Element::plain(format!(
+                    " {}",
+                    cert_info.expiration.format(&global_config.time_format)
+                )),
+            ];
+            if let Some(error) = &cert_info.verification_error {
+                line.push(Element::colored(
+                    format!(" (untrusted: {})", error),
+                    Color::Yellow,
+                ));
+            }
+            section.push(line);
+
+            if self.show_details {
+                let mut details = format!("{}{}", " ".repeat(2 * INDENT_WIDTH), cert_info.subject);
+                if !cert_info.alt_names.is_empty() {
+                    details.push_str(&format!(" ({})", cert_info.alt_names.join(", ")));
+                }
+                section.push(vec![Element::plain(details)]);
+            }
+        }
+
+        for failure in &self.failures {
+            section.push(vec![
+                Element::plain(format!("{}{} ", " ".repeat(INDENT_WIDTH), failure.name)),
+                Element::colored(format!("check failed: {}", failure.error), Color::Yellow),
+            ]);
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        self.certs
+            .iter()
+            .map(|cert_info| {
+                Metric::new(
+                    "motd_cert_expiry_timestamp",
+                    "Expiry time of the certificate in seconds since the epoch",
+                    cert_info.expiration.timestamp() as f64,
+                )
+                .label("name", &cert_info.name)
+            })
+            .collect()
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        let untrusted = self.certs.iter().filter_map(|cert_info| {
+            cert_info.verification_error.as_ref().map(|error| {
+                Problem::new(
+                    Severity::Warning,
+                    format!("{} is not trusted: {}", cert_info.name, error),
+                )
+            })
+        });
+        self.certs
+            .iter()
+            .filter_map(|cert_info| match cert_info.status {
+                CertStatus::Expired => Some(Problem::new(
+                    Severity::Critical,
+                    format!("{} has expired", cert_info.name),
+                )),
+                CertStatus::Expiring => Some(Problem::new(
+                    Severity::Warning,
+                    format!(
+                        "{} expires in {} days",
+                        cert_info.name,
+                        (cert_info.expiration - Utc::now()).num_days()
+                    ),
+                )),
+                CertStatus::Valid => None,
+            })
+            .chain(untrusted)
+            .chain(self.failures.iter().map(|failure| {
+                Problem::new(
+                    Severity::Warning,
+                    format!("{} could not be checked: {}", failure.name, failure.error),
+                )
+            }))
+            .collect()
+    }
+}
+
+fn expiration(cert: &X509Ref) -> Result<DateTime<Utc>, SSLCertsError> {
+    Ok(Utc.datetime_from_str(&format!("{}", cert.not_after()), "%B %_d %T %Y %Z")?)
+}
+
+fn subject(cert: &X509Ref) -> String {
+    cert.subject_name()
+        .entries()
+        .filter_map(|entry| {
+            let name = entry.object().nid().short_name().ok()?;
+            let value = entry.data().to_string().ok()?;
+            Some(format!("{}={}", name, value))
+        })
+        .join(", ")
+}
+
+fn alt_names(cert: &X509Ref) -> Vec<String> {
+    let ip_address = |bytes: &[u8]| -> Option<String> {
+        let ip = match bytes.len() {
+            4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+            16 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+            _ => return None,
+        };
+        Some(ip.to_string())
+    };
+    cert.subject_alt_names()
+        .into_iter()
+        .flatten()
+        .filter_map(|name| match name.dnsname() {
+            Some(dns_name) => Some(dns_name.to_string()),
+            None => name.ipaddress().and_then(ip_address),
+        })
+        .collect()
+}
+
+/// Reason why a chain does not verify, leaving out expiry which is reported on its own
+fn verification_error(result: X509VerifyResult) -> Option<String> {
+    match result == X509VerifyResult::OK || result.as_raw() == CERT_HAS_EXPIRED {
+        true => None,
+        false => Some(result.error_string().to_string()),
+    }
+}
+
+fn trust_store_file() -> Option<&'static Path> {
+    TRUST_STORE_FILES
+        .iter()
+        .map(Path::new)
+        .find(|path| path.is_file())
+}
+
+fn trust_store() -> Result<X509Store, SSLCertsError> {
+    let mut builder = X509StoreBuilder::new()?;
+    builder.set_default_paths()?;
+    if let Some(path) = trust_store_file() {
+        for cert in X509::stack_from_pem(&fs::read(path)?)? {
+            builder.add_cert(cert)?;
+        }
+    }
+    Ok(builder.build())
+}
+
+/// Verify a chain against the trust store, taking the first certificate as the leaf
+fn verify_chain(store: &X509StoreRef, certs: &[X509]) -> Result<X509VerifyResult, ErrorStack> {
+    let mut chain = Stack::new()?;
+    for cert in &certs[1..] {
+        chain.push(cert.clone())?;
+    }
+    X509StoreContext::new()?.init(store, &certs[0], &chain, |context| {
+        context.verify_cert()?;
+        Ok(context.error())
+    })
+}
+
+/// Read all certificates of a PEM bundle, a DER certificate or a PKCS#12 archive
+fn load_certs(path: &Path, password: &str) -> Result<Vec<X509>, SSLCertsError> {
+    let contents = fs::read(path)?;
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("p12" | "pfx") => {
+            let parsed = Pkcs12::from_der(&contents)?.parse2(password)?;
+            Ok(parsed
+                .cert
+                .into_iter()
+                .chain(parsed.ca.into_iter().flatten())
+                .collect())
+        }
+        _ if contents.windows(10).any(|window| window == b"-----BEGIN") => {
+            Ok(X509::stack_from_pem(&contents)?)
+        }
+        _ => Ok(vec![X509::from_der(&contents)?]),
+    }
+}
+
+fn has_cert_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|extension| CERT_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Files with certificates below a directory, with their path relative to `base`
+///
+/// Symlinks are followed, since Let's Encrypt links `live` into `archive`,
+/// but every directory is only walked once so that a loop of links ends
+fn walk_dir(
+    base: &Path,
+    dir: &Path,
+    visited: &mut HashSet<PathBuf>,
+) -> std::io::Result<Vec<(Option<PathBuf>, PathBuf)>> {
+    if !visited.insert(fs::canonicalize(dir)?) {
+        return Ok(vec![]);
+    }
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            files.extend(walk_dir(base, &path, visited)?);
+        } else if has_cert_extension(&path) {
+            let relative = path.strip_prefix(base).unwrap_or(&path).to_path_buf();
+            files.push((Some(relative), path));
+        }
+    }
+    Ok(files)
+}
+
+/// Files to inspect for a path of the configuration file, with their path relative to it
+/// (`None` if the path is a single file)
+fn expand_path(path: &str) -> Result<Vec<(Option<PathBuf>, PathBuf)>, SSLCertsError> {
+    let is_pattern = |part: &str| part.contains(['*', '?', '[']);
+    if !is_pattern(path) {
+        let path = PathBuf::from(path);
+        return match path.is_dir() {
+            true => Ok(walk_dir(&path, &path, &mut HashSet::new())?),
+            false => Ok(vec![(None, path)]),
+        };
+    }
+
+    // Relative paths of matches start after the last component without wildcards
+    let base: PathBuf = Path::new(path)
+        .components()
+        .take_while(|component| !is_pattern(&component.as_os_str().to_string_lossy()))
+        .collect();
+    let mut files = vec![];
+    let mut visited = HashSet::new();
+    for matched in glob::glob(path)? {
+        let matched = matched.map_err(std::io::Error::from)?;
+        if matched.is_dir() {
+            files.extend(walk_dir(&base, &matched, &mut visited)?);
+        } else {
+            let relative = matched
+                .strip_prefix(&base)
+                .unwrap_or(&matched)
+                .to_path_buf();
+            files.push((Some(relative), matched));
+        }
+    }
+    Ok(files)
+}
+
+/// Split an endpoint into the host (also used for SNI) and the port, which defaults to 443
+fn parse_endpoint(endpoint: &str) -> Result<(String, u16), SSLCertsError> {
+    let invalid = || SSLCertsError::InvalidEndpoint {
+        endpoint: endpoint.to_string(),
+    };
+    let (host, port) = match endpoint.rsplit_once(':') {
+        // A colon inside the brackets of an IPv6 address is not a port separator
+        Some((host, port)) if !port.ends_with(']') => {
+            (host, port.parse::<u16>().map_err(|_| invalid())?)
+        }
+        _ => (endpoint, DEFAULT_HTTPS_PORT),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.is_empty() {
+        true => Err(invalid()),
+        false => Ok((host.to_string(), port)),
+    }
+}
+
+/// Connect to the first address that accepts the connection
+/// Hosts may resolve to addresses that are not reachable from here, like IPv6 ones
+fn connect_any(addresses: impl Iterator<Item = SocketAddr>) -> std::io::Result<TcpStream> {
+    let mut result = Err(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "no addresses found",
+    ));
+    for address in addresses {
+        result = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// Certificate chain served by a TLS server and the result of verifying it
+fn get_endpoint_certs(endpoint: &str) -> Result<(Vec<X509>, X509VerifyResult), SSLCertsError> {
+    let (host, port) = parse_endpoint(endpoint)?;
+    let connect_error = |source| SSLCertsError::Connect {
+        endpoint: endpoint.to_string(),
+        source,
+    };
+
+    let addresses = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(connect_error)?;
+    let stream = connect_any(addresses).map_err(connect_error)?;
+    stream
+        .set_read_timeout(Some(CONNECT_TIMEOUT))
+        .map_err(connect_error)?;
+
+    // The certificates are only inspected, so the handshake succeeds even if they do not verify
+    let mut connector = SslConnector::builder(SslMethod::tls_client())?;
+    connector.set_verify(SslVerifyMode::NONE);
+    if let Some(path) = trust_store_file() {
+        connector.set_ca_file(path)?;
+    }
+    let stream = connector
+        .build()
+        .configure()?
+        .connect(&host, stream)
+        .map_err(|error| SSLCertsError::Handshake {
+            endpoint: endpoint.to_string(),
+            message: error.to_string(),
+        })?;
+
+    // On the client side the chain includes the certificate of the server itself
+    let certs: Vec<X509> = stream
+        .ssl()
+        .peer_cert_chain()
+        .into_iter()
+        .flatten()
+        .map(X509Ref::to_owned)
+        .collect();
+    match certs.is_empty() {
+        true => Err(SSLCertsError::NoCertificate {
+            endpoint: endpoint.to_string(),
+        }),
+        false => Ok((certs, stream.ssl().verify_result())),
+    }
 }
 
 impl SSLCerts {
-    pub fn print_or_error(self, global_config: &GlobalConfig) -> Result<(), SSLCertsError> {
+    /// `certs` must not be empty, the first one is taken as the leaf
+    fn cert_info(
+        &self,
+        name: String,
+        certs: &[X509],
+        verify_result: Option<X509VerifyResult>,
+    ) -> Result<CertInfo, SSLCertsError> {
+        let expiration = certs
+            .iter()
+            .map(|cert| expiration(cert))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .min()
+            .unwrap_or_else(Utc::now);
+
+        let now = Utc::now();
+        let status = if expiration < now {
+            CertStatus::Expired
+        } else if expiration < now + Duration::days(self.warning_days) {
+            CertStatus::Expiring
+        } else {
+            CertStatus::Valid
+        };
+        Ok(CertInfo {
+            name,
+            status,
+            expiration,
+            subject: subject(&certs[0]),
+            alt_names: alt_names(&certs[0]),
+            verification_error: verify_result.and_then(verification_error),
+        })
+    }
+
+    pub fn get_output_or_error(self) -> Result<SSLCertsOutput, SSLCertsError> {
         let mut cert_infos: Vec<CertInfo> = Vec::new();
+        let mut failures = vec![];
 
-        println!("SSL Certificates:");
-        for (name, path) in self.certs {
-            let cert = File::open(&path)?;
-            let cert = BufReader::new(cert);
-            let cert: Vec<u8> = cert.bytes().collect::<Result<_, _>>()?;
-            let cert = X509::from_pem(&cert)?;
-
-            let expiration =
-                Utc.datetime_from_str(&format!("{}", cert.not_after()), "%B %_d %T %Y %Z")?;
-
-            let now = Utc::now();
-            let status = if expiration < now {
-                format!("{}expired on{}", color::Fg(color::Red), style::Reset)
-            } else if expiration < now + Duration::days(30) {
-                format!("{}expiring on{}", color::Fg(color::Yellow), style::Reset)
-            } else {
-                format!("{}valid until{}", color::Fg(color::Green), style::Reset)
-            };
-            cert_infos.push(CertInfo {
-                name,
-                status,
-                expiration,
-            });
+        let store = match self.verify_chain && !self.certs.is_empty() {
+            true => Some(trust_store()?),
+            false => None,
+        };
+
+        for (name, cert_config) in &self.certs {
+            let (path, password) = cert_config.path_and_password();
+            let mut found = false;
+            for (relative, file) in expand_path(path)? {
+                let name = match relative {
+                    Some(relative) => format!("{}/{}", name, relative.display()),
+                    None => name.clone(),
+                };
+                // A file that cannot be read, like a PKCS#12 file with another password,
+                // should not hide the other files of a directory
+                let certs = match load_certs(&file, password) {
+                    Ok(certs) => certs,
+                    Err(source) => {
+                        found = true;
+                        failures.push(CheckFailure {
+                            name,
+                            error: SSLCertsError::Load {
+                                path: file.clone(),
+                                source: Box::new(source),
+                            }
+                            .to_string(),
+                        });
+                        continue;
+                    }
+                };
+                // Files found in a directory or by a pattern may hold just a private key
+                if certs.is_empty() {
+                    continue;
+                }
+                found = true;
+
+                // A file with just the leaf, like `cert.pem` of Let's Encrypt,
+                // cannot chain up to the trust store without its intermediates
+                let verify_result = match &store {
+                    Some(store) if certs.len() > 1 => Some(verify_chain(store, &certs)?),
+                    _ => None,
+                };
+                cert_infos.push(self.cert_info(name, &certs, verify_result)?);
+            }
+            if !found {
+                return Err(SSLCertsError::NoCertificateFound {
+                    path: path.to_string(),
+                });
+            }
+        }
+
+        // An endpoint that is down should not hide the certificates of all others
+        for (name, endpoint) in &self.endpoints {
+            match get_endpoint_certs(endpoint) {
+                Ok((certs, verify_result)) => {
+                    let verify_result = match self.verify_chain {
+                        true => Some(verify_result),
+                        false => None,
+                    };
+                    cert_infos.push(self.cert_info(name.clone(), &certs, verify_result)?);
+                }
+                Err(error) => failures.push(CheckFailure {
+                    name: name.clone(),
+                    error: error.to_string(),
+                }),
+            }
         }
+        failures.sort_by(|a, b| a.name.cmp(&b.name));
 
         match self.sort_method {
             SortMethod::Alphabetical => {
                 cert_infos.sort_by(|a, b| a.name.cmp(&b.name));
             }
             SortMethod::Expiration => {
-                cert_infos.sort_by(|a, b| a.expiration.cmp(&b.expiration));
+                cert_infos.sort_by_key(|a| a.expiration);
             }
             SortMethod::Manual => {}
         }
 
-        for cert_info in cert_infos.into_iter() {
-            println!(
-                "{}{} {} {}",
-                " ".repeat(INDENT_WIDTH),
-                cert_info.name,
-                cert_info.status,
-                cert_info.expiration.format(&global_config.time_format)
-            );
-        }
+        Ok(SSLCertsOutput {
+            show_details: self.show_details,
+            certs: cert_infos,
+            failures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::ssl::{SniError, SslAcceptor};
+    use openssl::x509::extension::SubjectAlternativeName;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    fn generate_cert(days: u32) -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "localhost").unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(days).unwrap())
+            .unwrap();
+        let alt_names = SubjectAlternativeName::new()
+            .dns("localhost")
+            .build(&builder.x509v3_context(None, None))
+            .unwrap();
+        builder.append_extension(alt_names).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        (builder.build(), key)
+    }
+
+    /// TLS server on a free port of 127.0.0.1 that records the server names clients ask for
+    fn serve(cert: &X509, key: &PKey<Private>) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let server_names = Arc::new(Mutex::new(vec![]));
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_certificate(cert).unwrap();
+        acceptor.set_private_key(key).unwrap();
+        let names = server_names.clone();
+        acceptor.set_servername_callback(move |ssl, _| {
+            if let Some(name) = ssl.servername(openssl::ssl::NameType::HOST_NAME) {
+                names.lock().unwrap().push(name.to_string());
+            }
+            Ok::<(), SniError>(())
+        });
+        let acceptor = acceptor.build();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = acceptor.accept(stream);
+            }
+        });
+        (port, server_names)
+    }
+
+    #[test]
+    fn endpoint_certificate_and_sni() {
+        let (cert, key) = generate_cert(10);
+        let (port, server_names) = serve(&cert, &key);
+
+        // `localhost` may resolve to ::1 first, where nothing listens
+        let (certs, _) = get_endpoint_certs(&format!("localhost:{}", port)).unwrap();
+        assert_eq!(certs.len(), 1);
+        assert_eq!(expiration(&certs[0]).unwrap(), expiration(&cert).unwrap());
+        assert_eq!(alt_names(&certs[0]), vec!["localhost"]);
+        assert_eq!(*server_names.lock().unwrap(), vec!["localhost"]);
+    }
+
+    #[test]
+    fn later_addresses_are_tried() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let stream = connect_any([closed, open].into_iter()).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), open);
+        assert!(connect_any([closed].into_iter()).is_err());
+        assert!(connect_any([].into_iter()).is_err());
+    }
+
+    #[test]
+    fn unreachable_endpoint_is_reported_with_the_others() {
+        let (cert, key) = generate_cert(10);
+        let (port, _) = serve(&cert, &key);
+        // Nothing listens on the port of a listener that was just dropped
+        let closed_port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let config = format!(
+            "verify_chain = false\n[endpoints]\nUp = \"127.0.0.1:{}\"\nDown = \"127.0.0.1:{}\"\n",
+            port, closed_port
+        );
+        let ssl_certs: SSLCerts = toml::from_str(&config).unwrap();
+        let output = ssl_certs.get_output_or_error().unwrap();
+
+        assert_eq!(output.certs.len(), 1);
+        assert_eq!(output.certs[0].name, "Up");
+        assert!(matches!(output.certs[0].status, CertStatus::Expiring));
+        assert_eq!(output.failures.len(), 1);
+        assert_eq!(output.failures[0].name, "Down");
+        assert_eq!(output.problems().len(), 2);
+    }
+
+    #[test]
+    fn directory_walk_survives_loops_and_unreadable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = generate_cert(100);
+        fs::create_dir(dir.path().join("live")).unwrap();
+        fs::write(dir.path().join("live/cert.pem"), cert.to_pem().unwrap()).unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("live/loop")).unwrap();
+        let pkcs12 = Pkcs12::builder()
+            .name("test")
+            .pkey(&key)
+            .cert(&cert)
+            .build2("secret")
+            .unwrap();
+        fs::write(dir.path().join("other.p12"), pkcs12.to_der().unwrap()).unwrap();
+
+        let config = format!(
+            "verify_chain = false\n[certs]\nDir = {:?}\n",
+            dir.path().display().to_string()
+        );
+        let ssl_certs: SSLCerts = toml::from_str(&config).unwrap();
+        let output = ssl_certs.get_output_or_error().unwrap();
 
-        Ok(())
+        let names: Vec<&str> = output.certs.iter().map(|cert| cert.name.as_str()).collect();
+        assert_eq!(names, ["Dir/live/cert.pem"]);
+        assert_eq!(output.failures.len(), 1);
+        assert_eq!(output.failures[0].name, "Dir/other.p12");
+        assert_eq!(output.problems().len(), 1);
     }
 }
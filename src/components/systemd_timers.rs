@@ -0,0 +1,298 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::Connection;
+
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
+use crate::config::global_config::GlobalConfig;
+use crate::constants::INDENT_WIDTH;
+use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+use crate::severity::{Problem, Severity};
+use crate::systemd::{self, ManagerProxy, ServiceProxy, TimerProxy};
+
+#[derive(Debug, Deserialize)]
+pub struct SystemdTimers {
+    /// Show the timers of the user instance of systemd instead of the system
+    #[serde(default)]
+    user: bool,
+    /// Glob patterns of the timers to show, all timers if empty
+    #[serde(default)]
+    timers: Vec<String>,
+}
+
+#[async_trait]
+impl Component for SystemdTimers {
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error().await, "Systemd timers error")
+    }
+    default_prepare!();
+}
+
+#[derive(Error, Debug)]
+pub enum SystemdTimersError {
+    #[error("No timers found")]
+    NoTimers,
+
+    #[error("Could not connect to systemd on the {bus} bus: {source}")]
+    Connect {
+        bus: &'static str,
+        source: zbus::Error,
+    },
+
+    #[error(transparent)]
+    DBus(#[from] zbus::Error),
+}
+
+/// State of one timer and of the unit it activates
+#[derive(Serialize)]
+struct Timer {
+    /// Name of the timer without the `.timer` suffix
+    name: String,
+    unit: String,
+    last_trigger: Option<DateTime<Local>>,
+    next_elapse: Option<DateTime<Local>>,
+    /// Active state of the activated unit, `activating` while it runs
+    unit_state: Option<String>,
+    /// How the last run of the activated service ended, for example `success` or `exit-code`
+    result: Option<String>,
+}
+
+impl Timer {
+    fn running(&self) -> bool {
+        matches!(
+            self.unit_state.as_deref(),
+            Some("activating" | "deactivating" | "reloading")
+        )
+    }
+
+    /// A service that never ran reports `success` as well
+    fn failed(&self) -> bool {
+        self.result
+            .as_deref()
+            .is_some_and(|result| result != "success")
+    }
+
+    /// Outcome of the last run to show next to it, if there was one
+    fn outcome(&self) -> Option<(&str, Color)> {
+        if self.running() {
+            Some(("running", Color::Yellow))
+        } else if self.failed() {
+            self.result.as_deref().map(|result| (result, Color::Red))
+        } else if self.result.is_some() && self.last_trigger.is_some() {
+            Some(("success", Color::Green))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SystemdTimersOutput {
+    timers: Vec<Timer>,
+}
+
+impl ComponentOutput for SystemdTimersOutput {
+    fn to_section(&self, global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("Timers"));
+        let format_time = |time: Option<DateTime<Local>>| match time {
+            Some(time) => time.format(&global_config.time_format).to_string(),
+            None => "never".to_string(),
+        };
+
+        let name_padding = self
+            .timers
+            .iter()
+            .map(|timer| timer.name.len())
+            .max()
+            .unwrap_or(0);
+        let last_padding = self
+            .timers
+            .iter()
+            .map(|timer| format_time(timer.last_trigger).len())
+            .max()
+            .unwrap_or(0);
+        let outcome_padding = self
+            .timers
+            .iter()
+            .filter_map(|timer| timer.outcome())
+            .map(|(outcome, _)| outcome.len())
+            .max()
+            .unwrap_or(0);
+
+        for timer in &self.timers {
+            let last = format_time(timer.last_trigger);
+            let outcome = timer.outcome();
+            let outcome_len = outcome.map(|(outcome, _)| outcome.len()).unwrap_or(0);
+
+            let mut line = vec![Element::plain(format!(
+                "{}{}: {}last {}{}",
+                " ".repeat(INDENT_WIDTH),
+                timer.name,
+                " ".repeat(name_padding - timer.name.len()),
+                last,
+                " ".repeat(last_padding - last.len()),
+            ))];
+            // The column of outcomes is left out when no timer has one
+            if outcome_padding > 0 {
+                line.push(Element::plain(" "));
+            }
+            if let Some((outcome, color)) = outcome {
+                line.push(Element::colored(outcome, color));
+            }
+            line.push(Element::plain(format!(
+                "{} next {}",
+                " ".repeat(outcome_padding - outcome_len),
+                format_time(timer.next_elapse)
+            )));
+            section.push(line);
+        }
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let mut metrics = vec![];
+        for timer in &self.timers {
+            if let Some(last_trigger) = timer.last_trigger {
+                metrics.push(
+                    Metric::new(
+                        "motd_timer_last_trigger_timestamp",
+                        "When the systemd timer last elapsed",
+                        last_trigger.timestamp() as f64,
+                    )
+                    .label("name", &timer.name)
+                    .label("unit", &timer.unit),
+                );
+            }
+            if let Some(next_elapse) = timer.next_elapse {
+                metrics.push(
+                    Metric::new(
+                        "motd_timer_next_elapse_timestamp",
+                        "When the systemd timer elapses next",
+                        next_elapse.timestamp() as f64,
+                    )
+                    .label("name", &timer.name)
+                    .label("unit", &timer.unit),
+                );
+            }
+            if timer.result.is_some() {
+                metrics.push(
+                    Metric::new(
+                        "motd_timer_last_run_success",
+                        "Whether the last run of the unit activated by the systemd timer succeeded",
+                        !timer.failed(),
+                    )
+                    .label("name", &timer.name)
+                    .label("unit", &timer.unit),
+                );
+            }
+        }
+        metrics
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        self.timers
+            .iter()
+            .filter(|timer| timer.failed() && !timer.running())
+            .map(|timer| {
+                Problem::new(
+                    Severity::Critical,
+                    format!(
+                        "{} last run failed ({})",
+                        timer.name,
+                        timer.result.as_deref().unwrap_or_default()
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Properties of a timer, and of the unit it activates if that is a service
+async fn get_timer(
+    connection: &Connection,
+    manager: &ManagerProxy<'_>,
+    name: String,
+    path: OwnedObjectPath,
+) -> Result<Timer, SystemdTimersError> {
+    let proxy = TimerProxy::builder(connection)
+        .path(path)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let unit = proxy.unit().await?;
+    let last_trigger = proxy.last_trigger_usec().await.ok();
+    let next_elapse = proxy.next_elapse_usec_realtime().await.ok();
+
+    let unit_status = manager
+        .list_units_by_names(&[unit.as_str()])
+        .await?
+        .into_iter()
+        .next()
+        .filter(|unit_status| unit_status.load_state == "loaded");
+    let result = match &unit_status {
+        Some(unit_status) if unit.ends_with(".service") => ServiceProxy::builder(connection)
+            .path(unit_status.path.clone())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?
+            .result()
+            .await
+            .ok(),
+        _ => None,
+    };
+
+    Ok(Timer {
+        name: name.strip_suffix(".timer").unwrap_or(&name).to_string(),
+        unit,
+        last_trigger: last_trigger.and_then(systemd::timestamp),
+        next_elapse: next_elapse.and_then(systemd::timestamp),
+        unit_state: unit_status.map(|unit_status| unit_status.active_state),
+        result,
+    })
+}
+
+impl SystemdTimers {
+    pub async fn get_output_or_error(&self) -> Result<SystemdTimersOutput, SystemdTimersError> {
+        let connection =
+            systemd::connect(self.user)
+                .await
+                .map_err(|source| SystemdTimersError::Connect {
+                    bus: systemd::bus_name(self.user),
+                    source,
+                })?;
+        let manager = ManagerProxy::new(&connection).await?;
+
+        // `certbot` means `certbot.timer`, as a bare service name means the `.service` unit
+        let patterns: Vec<String> = match self.timers.is_empty() {
+            true => vec!["*.timer".to_string()],
+            false => self
+                .timers
+                .iter()
+                .map(|pattern| match pattern.ends_with(".timer") {
+                    true => pattern.clone(),
+                    false => format!("{}.timer", pattern),
+                })
+                .collect(),
+        };
+        let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+        let mut units = manager.list_units_by_patterns(&[], &patterns).await?;
+        units.retain(|unit| unit.name.ends_with(".timer"));
+        units.sort_by(|a, b| a.name.cmp(&b.name));
+        if units.is_empty() {
+            return Err(SystemdTimersError::NoTimers);
+        }
+
+        let mut timers = vec![];
+        for unit in units {
+            timers.push(get_timer(&connection, &manager, unit.name, unit.path).await?);
+        }
+
+        Ok(SystemdTimersOutput { timers })
+    }
+}
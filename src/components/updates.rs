@@ -0,0 +1,347 @@
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::command::{is_installed, BetterCommand, BetterCommandError};
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
+use crate::config::global_config::GlobalConfig;
+use crate::constants::INDENT_WIDTH;
+use crate::default_prepare;
+use crate::render::prometheus::Metric;
+use crate::section::{Color, Element, Section};
+use crate::severity::{Problem, Severity};
+
+/// Statistics that update-notifier keeps up to date on Ubuntu after every `apt update`
+const UPDATE_NOTIFIER_PATH: &str = "/var/lib/update-notifier/updates-available";
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum PackageManager {
+    Apt,
+    Dnf,
+    Pacman,
+}
+
+impl PackageManager {
+    /// The first supported package manager that is installed
+    fn detect() -> Option<Self> {
+        [
+            ("apt-get", PackageManager::Apt),
+            ("dnf", PackageManager::Dnf),
+            ("pacman", PackageManager::Pacman),
+        ]
+        .into_iter()
+        .find(|(executable, _)| is_installed(executable))
+        .map(|(_, package_manager)| package_manager)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(try_from = "String")]
+enum PackageManagerSetting {
+    /// Use the first package manager that is installed
+    #[default]
+    Auto,
+    Fixed(PackageManager),
+}
+
+impl TryFrom<String> for PackageManagerSetting {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "auto" => Ok(PackageManagerSetting::Auto),
+            "apt" => Ok(PackageManagerSetting::Fixed(PackageManager::Apt)),
+            "dnf" => Ok(PackageManagerSetting::Fixed(PackageManager::Dnf)),
+            "pacman" => Ok(PackageManagerSetting::Fixed(PackageManager::Pacman)),
+            _ => Err(format!(
+                "unknown package manager `{}`, expected one of `auto`, `apt`, `dnf`, `pacman`",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Updates {
+    #[serde(default)]
+    package_manager: PackageManagerSetting,
+}
+
+#[async_trait]
+impl Component for Updates {
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Updates error")
+    }
+    default_prepare!();
+}
+
+#[derive(Error, Debug)]
+pub enum UpdatesError {
+    #[error("None of the supported package managers (apt, dnf, pacman) is installed")]
+    NoPackageManager,
+
+    #[error(transparent)]
+    BetterCommand(#[from] BetterCommandError),
+}
+
+#[derive(Serialize)]
+pub struct UpdatesOutput {
+    package_manager: PackageManager,
+    updates: usize,
+    /// `None` if the package manager does not know which updates fix security issues
+    security_updates: Option<usize>,
+}
+
+impl ComponentOutput for UpdatesOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(Some("Updates"));
+        let indent = Element::plain(" ".repeat(INDENT_WIDTH));
+
+        let line = match (self.updates, self.security_updates) {
+            (0, _) => vec![
+                indent,
+                Element::colored("System is up to date", Color::Green),
+            ],
+            (updates, Some(security_updates)) if security_updates > 0 => vec![
+                indent,
+                Element::plain(format!("{} can be applied, ", plural(updates, "update"))),
+                Element::colored(
+                    format!("{} of them security", security_updates),
+                    Severity::Warning.bar_color(),
+                ),
+            ],
+            (updates, _) => vec![
+                indent,
+                Element::plain(format!("{} can be applied", plural(updates, "update"))),
+            ],
+        };
+        section.push(line);
+
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        let mut metrics = vec![Metric::new(
+            "motd_updates_pending",
+            "Number of packages that can be upgraded",
+            self.updates as f64,
+        )];
+        if let Some(security_updates) = self.security_updates {
+            metrics.push(Metric::new(
+                "motd_updates_security",
+                "Number of packages with security updates",
+                security_updates as f64,
+            ));
+        }
+        metrics
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        match self.security_updates {
+            Some(security_updates) if security_updates > 0 => vec![Problem::new(
+                Severity::Warning,
+                format!("{} pending", plural(security_updates, "security update")),
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        count => format!("{} {}s", count, noun),
+    }
+}
+
+/// Updates and security updates from the statistics of update-notifier, if it is installed
+fn read_update_notifier() -> Option<(usize, usize)> {
+    parse_update_notifier(&fs::read_to_string(UPDATE_NOTIFIER_PATH).ok()?)
+}
+
+/// The file is in the language of the system, so it is only used if it can be understood
+/// Security updates of Ubuntu Pro that are enabled are counted along with the standard ones
+fn parse_update_notifier(contents: &str) -> Option<(usize, usize)> {
+    lazy_static! {
+        static ref UPDATES_REGEX: Regex =
+            Regex::new(r"(\d+) (?:updates?|packages?) can be (?:applied|updated|installed)")
+                .unwrap();
+        static ref SECURITY_REGEX: Regex = Regex::new(
+            r"(\d+) (?:of these )?updates? (?:is|are) (?:an? )?(?:standard |ESM \w+ |UA \w+ )?security"
+        )
+        .unwrap();
+    }
+
+    // The file is left empty when there are no updates
+    if contents.trim().is_empty() {
+        return Some((0, 0));
+    }
+    let updates = UPDATES_REGEX.captures(contents)?[1].parse().ok()?;
+    let security_updates = SECURITY_REGEX
+        .captures_iter(contents)
+        .filter_map(|captures| captures[1].parse::<usize>().ok())
+        .sum();
+    Some((updates, security_updates))
+}
+
+fn get_apt_updates() -> Result<(usize, Option<usize>), UpdatesError> {
+    if Path::new(UPDATE_NOTIFIER_PATH).exists() {
+        if let Some((updates, security_updates)) = read_update_notifier() {
+            return Ok((updates, Some(security_updates)));
+        }
+    }
+
+    // Simulating an upgrade only reads the package lists, so it does not need root
+    let output = BetterCommand::new("apt-get")
+        .args(["--simulate", "-o", "Debug::NoLocking=true", "dist-upgrade"])
+        .check_status_and_get_output_string()?;
+    let installs: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("Inst "))
+        .collect();
+    let security_updates = installs
+        .iter()
+        .filter(|line| line.to_lowercase().contains("-security"))
+        .count();
+    Ok((installs.len(), Some(security_updates)))
+}
+
+/// Runs dnf from its cache, so logging in does not wait for the metadata to be downloaded
+fn run_dnf(args: &[&str]) -> Result<String, UpdatesError> {
+    let output = BetterCommand::new("dnf")
+        .args(["--quiet", "--cacheonly"])
+        .args(args)
+        .output()?;
+    // `check-update` exits with 100 if there are updates
+    match output.status.code() {
+        Some(0 | 100) => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        exit_code => Err(BetterCommandError::ExitStatusError {
+            executable: "dnf".to_string(),
+            exit_code: exit_code.unwrap_or(-1),
+            error: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+        .into()),
+    }
+}
+
+fn get_dnf_updates() -> Result<(usize, Option<usize>), UpdatesError> {
+    // Packages are listed as `name.arch version repository`,
+    // followed by a list of obsoleted packages which are not counted
+    let updates = run_dnf(&["check-update"])?
+        .lines()
+        .take_while(|line| !line.starts_with("Obsoleting"))
+        .filter(|line| line.split_whitespace().count() == 3)
+        .count();
+
+    // Advisories are listed as `id type package`, with a line for every package they fix
+    let advisories = run_dnf(&["updateinfo", "list", "--security"])?;
+    let mut security_packages: Vec<&str> = advisories
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(2))
+        .collect();
+    security_packages.sort_unstable();
+    security_packages.dedup();
+
+    Ok((updates, Some(security_packages.len())))
+}
+
+/// Arch has no security metadata in its repositories, so only the number of updates is known
+fn get_pacman_updates() -> Result<(usize, Option<usize>), UpdatesError> {
+    // `pacman -Qu` checks the local package database without syncing it,
+    // and exits with 1 if there are no updates
+    let output = BetterCommand::new("pacman")
+        .arg("-Qu")
+        .get_output_string()?;
+    Ok((output.lines().filter(|line| !line.is_empty()).count(), None))
+}
+
+impl Updates {
+    pub fn get_output_or_error(self) -> Result<UpdatesOutput, UpdatesError> {
+        let package_manager = match self.package_manager {
+            PackageManagerSetting::Auto => {
+                PackageManager::detect().ok_or(UpdatesError::NoPackageManager)?
+            }
+            PackageManagerSetting::Fixed(package_manager) => package_manager,
+        };
+
+        let (updates, security_updates) = match package_manager {
+            PackageManager::Apt => get_apt_updates()?,
+            PackageManager::Dnf => get_dnf_updates()?,
+            PackageManager::Pacman => get_pacman_updates()?,
+        };
+
+        Ok(UpdatesOutput {
+            package_manager,
+            updates,
+            security_updates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_notifier() {
+        for (contents, expected) in [
+            ("", (0, 0)),
+            ("\n0 updates can be applied immediately.\n\n", (0, 0)),
+            (
+                "\n1 update can be applied immediately.\n\
+                 1 of these updates is a standard security update.\n\
+                 To see these additional updates run: apt list --upgradable\n\n",
+                (1, 1),
+            ),
+            (
+                "\n17 updates can be applied immediately.\n\
+                 8 of these updates are standard security updates.\n\
+                 To see these additional updates run: apt list --upgradable\n\n",
+                (17, 8),
+            ),
+            (
+                "\nExpanded Security Maintenance for Applications is enabled.\n\n\
+                 12 updates can be applied immediately.\n\
+                 5 of these updates are standard security updates.\n\
+                 1 of these updates is an ESM Apps security update.\n\
+                 To see these additional updates run: apt list --upgradable\n\n",
+                (12, 6),
+            ),
+            (
+                "\nExpanded Security Maintenance for Applications is not enabled.\n\n\
+                 3 updates can be applied immediately.\n\
+                 To see these additional updates run: apt list --upgradable\n\n\
+                 4 additional security updates can be applied with ESM Apps.\n\
+                 Learn more about enabling ESM Apps service at https://ubuntu.com/esm\n\n",
+                (3, 0),
+            ),
+            // Ubuntu 20.04 and older
+            (
+                "\n42 packages can be updated.\n10 updates are security updates.\n\n",
+                (42, 10),
+            ),
+            (
+                "\n1 package can be updated.\n1 update is a security update.\n\n",
+                (1, 1),
+            ),
+            (
+                "\n5 updates can be installed immediately.\n\
+                 2 of these updates are UA Infra security updates.\n\n",
+                (5, 2),
+            ),
+        ] {
+            assert_eq!(
+                parse_update_notifier(contents),
+                Some(expected),
+                "{}",
+                contents
+            );
+        }
+        assert_eq!(parse_update_notifier("42 Aktualisierungen"), None);
+    }
+}
@@ -1,11 +1,15 @@
 use async_trait::async_trait;
 use humantime::format_duration;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use systemstat::{Platform, System};
 
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::default_prepare;
+use crate::render::json::serialize_seconds;
+use crate::render::prometheus::Metric;
+use crate::section::{Element, Section};
 
 #[derive(Debug, Deserialize)]
 pub struct Uptime {
@@ -14,20 +18,48 @@ pub struct Uptime {
 
 #[async_trait]
 impl Component for Uptime {
-    async fn print(self: Box<Self>, _global_config: &GlobalConfig, _width: Option<usize>) {
-        self.print_or_error()
-            .unwrap_or_else(|err| println!("Uptime error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Uptime error")
     }
     default_prepare!();
 }
 
+#[derive(Serialize)]
+pub struct UptimeOutput {
+    #[serde(skip)]
+    prefix: String,
+    #[serde(rename = "uptime_seconds", serialize_with = "serialize_seconds")]
+    uptime: Duration,
+}
+
+impl ComponentOutput for UptimeOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(None);
+        section.push(vec![Element::plain(format!(
+            "{} {}",
+            self.prefix,
+            format_duration(self.uptime)
+        ))]);
+        section
+    }
+
+    fn metrics(&self) -> Vec<Metric> {
+        vec![Metric::new(
+            "motd_uptime_seconds",
+            "Time since the system booted",
+            self.uptime.as_secs_f64(),
+        )]
+    }
+}
+
 impl Uptime {
-    pub fn print_or_error(self) -> Result<(), std::io::Error> {
+    pub fn get_output_or_error(self) -> Result<UptimeOutput, std::io::Error> {
         let sys = System::new();
         let uptime = sys.uptime()?;
-        println!("{} {}", self.prefix, format_duration(uptime));
 
-        Ok(())
+        Ok(UptimeOutput {
+            prefix: self.prefix,
+            uptime,
+        })
     }
 }
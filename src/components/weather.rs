@@ -1,13 +1,13 @@
 use async_trait::async_trait;
-use serde::Deserialize;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use thiserror::Error;
 use ureq;
-use std::time::Duration;
 
-use crate::component::Component;
+use crate::component::{output_or_error, BoxedOutput, Component, ComponentOutput};
 use crate::config::global_config::GlobalConfig;
 use crate::default_prepare;
+use crate::section::{Element, Section};
 
 #[derive(Debug, Deserialize)]
 pub struct Weather {
@@ -34,14 +34,28 @@ impl Default for Timeout {
 
 #[async_trait]
 impl Component for Weather {
-    async fn print(self: Box<Self>, _global_config: &GlobalConfig, _width: Option<usize>) {
-        self.print_or_error()
-            .unwrap_or_else(|err| println!("Weather error: {}", err));
-        println!();
+    async fn get_output(self: Box<Self>, _global_config: &GlobalConfig) -> BoxedOutput {
+        output_or_error(self.get_output_or_error(), "Weather error")
     }
     default_prepare!();
 }
 
+#[derive(Serialize)]
+pub struct WeatherOutput {
+    /// The reply of the weather service, which may contain its own terminal escape codes
+    text: String,
+}
+
+impl ComponentOutput for WeatherOutput {
+    fn to_section(&self, _global_config: &GlobalConfig, _width: Option<usize>) -> Section {
+        let mut section = Section::new(None);
+        for line in self.text.lines() {
+            section.push(vec![Element::plain(line)]);
+        }
+        section
+    }
+}
+
 #[derive(Debug, Deserialize)]
 enum WeatherStyle {
     #[serde(alias = "oneline")]
@@ -66,7 +80,8 @@ pub enum WeatherError {
 }
 
 impl Weather {
-    pub fn print_or_error(self) -> Result<(), WeatherError> {
+    #[allow(clippy::result_large_err)]
+    pub fn get_output_or_error(self) -> Result<WeatherOutput, WeatherError> {
         let url = match self.url {
             Some(url) => url,
             None => {
@@ -82,8 +97,7 @@ impl Weather {
             }
         };
 
-        let mut agent = ureq::AgentBuilder::new().
-            timeout(Duration::from_secs(self.timeout.0));
+        let mut agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(self.timeout.0));
         if let Some(proxy) = self.proxy {
             let proxy = ureq::Proxy::new(proxy)?;
             agent = agent.proxy(proxy);
@@ -114,11 +128,8 @@ impl Weather {
             .collect::<Vec<String>>()
             .join("");
 
-        let mut out = std::io::stdout();
-        out.write_all(&[first_line.as_bytes(), "\n".as_bytes()].concat())?;
-        out.write_all(body.as_bytes())?;
-        out.flush()?;
-
-        Ok(())
+        Ok(WeatherOutput {
+            text: [first_line, "\n".to_string(), body].concat(),
+        })
     }
 }
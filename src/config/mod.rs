@@ -1,18 +1,25 @@
-use serde::de::{Deserialize, Visitor};
+use serde::de::{Deserialize, Error, Visitor};
 
 pub mod get_config;
 pub mod global_config;
 
 use crate::component::BoxedComponent;
 use crate::components::banner::Banner;
+use crate::components::cpu::Cpu;
 use crate::components::docker::Docker;
 use crate::components::fail_2_ban::Fail2Ban;
 use crate::components::filesystem::Filesystems;
 use crate::components::last_login::LastLogin;
 use crate::components::last_run::LastRun;
 use crate::components::memory::Memory;
+use crate::components::network::Network;
+use crate::components::reboot_required::RebootRequired;
+use crate::components::sensors::Sensors;
 use crate::components::service_status::{ServiceStatus, UserServiceStatus};
+use crate::components::ssh_failures::SshFailures;
 use crate::components::ssl_certs::SSLCerts;
+use crate::components::systemd_timers::SystemdTimers;
+use crate::components::updates::Updates;
 use crate::components::uptime::Uptime;
 use crate::components::weather::Weather;
 use global_config::GlobalConfig;
@@ -24,6 +31,7 @@ use global_config::GlobalConfig;
 enum Fields {
     Global,
     Banner,
+    Cpu,
     Docker,
     #[serde(rename = "fail_2_ban")]
     Fail2Ban,
@@ -31,10 +39,16 @@ enum Fields {
     LastLogin,
     LastRun,
     Memory,
+    Network,
+    RebootRequired,
+    Sensors,
     ServiceStatus,
     UserServiceStatus,
+    SshFailures,
     #[serde(rename = "ssl_certificates")]
     SSLCerts,
+    SystemdTimers,
+    Updates,
     Uptime,
     Weather,
 }
@@ -43,8 +57,9 @@ enum Fields {
 /// The order of the components in the vector is the order they appear in the configuration file
 /// and is the order in which they should be printed
 /// This way, users can configure the order of components by shifting lines in the config file
+/// Each component is stored with the name of its section in the config file (e.g. `fail_2_ban`)
 pub struct Config {
-    pub components: Vec<BoxedComponent>,
+    pub components: Vec<(String, BoxedComponent)>,
     pub global: GlobalConfig,
 }
 
@@ -73,72 +88,36 @@ impl<'de> Deserialize<'de> for Config {
                     global: GlobalConfig::default(),
                 };
 
-                while let Some(key) = map.next_key()? {
-                    match key {
+                while let Some(name) = map.next_key::<String>()? {
+                    let key: Fields = serde_plain::from_str(&name).map_err(A::Error::custom)?;
+                    let component: BoxedComponent = match key {
                         Fields::Global => {
                             result.global = map.next_value()?;
+                            continue;
                         }
-                        Fields::Banner => {
-                            result
-                                .components
-                                .push(Box::new(map.next_value::<Banner>()?));
-                        }
-                        Fields::Docker => {
-                            result.components.push(Box::new(Docker {
-                                containers: map.next_value()?,
-                            }));
-                        }
-                        Fields::Fail2Ban => {
-                            result
-                                .components
-                                .push(Box::new(map.next_value::<Fail2Ban>()?));
-                        }
-                        Fields::Filesystems => {
-                            result
-                                .components
-                                .push(Box::new(Filesystems::new(map.next_value()?)));
-                        }
-                        Fields::LastLogin => {
-                            result.components.push(Box::new(LastLogin {
-                                users: map.next_value()?,
-                            }));
-                        }
-                        Fields::LastRun => {
-                            result
-                                .components
-                                .push(Box::new(map.next_value::<LastRun>()?));
-                        }
-                        Fields::Memory => {
-                            result
-                                .components
-                                .push(Box::new(map.next_value::<Memory>()?));
-                        }
-                        Fields::ServiceStatus => {
-                            result.components.push(Box::new(ServiceStatus {
-                                services: map.next_value()?,
-                            }));
-                        }
+                        Fields::Banner => Box::new(map.next_value::<Banner>()?),
+                        Fields::Cpu => Box::new(map.next_value::<Cpu>()?),
+                        Fields::Docker => Box::new(map.next_value::<Docker>()?),
+                        Fields::Fail2Ban => Box::new(map.next_value::<Fail2Ban>()?),
+                        Fields::Filesystems => Box::new(map.next_value::<Filesystems>()?),
+                        Fields::LastLogin => Box::new(map.next_value::<LastLogin>()?),
+                        Fields::LastRun => Box::new(map.next_value::<LastRun>()?),
+                        Fields::Memory => Box::new(map.next_value::<Memory>()?),
+                        Fields::Network => Box::new(map.next_value::<Network>()?),
+                        Fields::RebootRequired => Box::new(map.next_value::<RebootRequired>()?),
+                        Fields::Sensors => Box::new(map.next_value::<Sensors>()?),
+                        Fields::ServiceStatus => Box::new(map.next_value::<ServiceStatus>()?),
                         Fields::UserServiceStatus => {
-                            result.components.push(Box::new(UserServiceStatus {
-                                services: map.next_value()?,
-                            }));
-                        }
-                        Fields::SSLCerts => {
-                            result
-                                .components
-                                .push(Box::new(map.next_value::<SSLCerts>()?));
-                        }
-                        Fields::Uptime => {
-                            result
-                                .components
-                                .push(Box::new(map.next_value::<Uptime>()?));
-                        }
-                        Fields::Weather => {
-                            result
-                                .components
-                                .push(Box::new(map.next_value::<Weather>()?));
+                            Box::new(map.next_value::<UserServiceStatus>()?)
                         }
-                    }
+                        Fields::SshFailures => Box::new(map.next_value::<SshFailures>()?),
+                        Fields::SSLCerts => Box::new(map.next_value::<SSLCerts>()?),
+                        Fields::SystemdTimers => Box::new(map.next_value::<SystemdTimers>()?),
+                        Fields::Updates => Box::new(map.next_value::<Updates>()?),
+                        Fields::Uptime => Box::new(map.next_value::<Uptime>()?),
+                        Fields::Weather => Box::new(map.next_value::<Weather>()?),
+                    };
+                    result.components.push((name, component));
                 }
                 Ok(result)
             }
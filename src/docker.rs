@@ -0,0 +1,208 @@
+use docker_api::Docker;
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+use serde::Deserialize;
+use std::env;
+use std::fs::Permissions;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tempfile::TempDir;
+use thiserror::Error;
+use tokio::io::copy_bidirectional;
+use tokio::net::{TcpStream, UnixListener};
+use tokio::task::JoinHandle;
+use tokio_openssl::SslStream;
+
+/// Sockets of rootful Docker and Podman, relative to `/`,
+/// and of rootless Docker and Podman, relative to `$XDG_RUNTIME_DIR`
+const SYSTEM_SOCKETS: [&str; 2] = ["/var/run/docker.sock", "/run/podman/podman.sock"];
+const USER_SOCKETS: [&str; 2] = ["docker.sock", "podman/podman.sock"];
+
+/// Where to find the Docker (or Podman) API
+#[derive(Debug, Deserialize)]
+pub struct Endpoint {
+    /// `unix:///path/to/docker.sock` or `tcp://host:port`
+    host: Option<String>,
+    /// Directory with `ca.pem`, `cert.pem` and `key.pem` to connect to a `tcp://` host with TLS
+    tls_cert_path: Option<PathBuf>,
+    /// Whether to verify the certificate of the host against `ca.pem`
+    #[serde(default = "default_tls_verify")]
+    tls_verify: bool,
+}
+
+fn default_tls_verify() -> bool {
+    true
+}
+
+#[derive(Error, Debug)]
+pub enum EndpointError {
+    #[error("Unsupported Docker host `{0}', expected unix:// or tcp://")]
+    UnsupportedHost(String),
+
+    #[error("Docker socket {0} not found")]
+    SocketNotFound(String),
+
+    #[error("TLS connection to {0} failed: {1}")]
+    Tls(String, Box<dyn std::error::Error + Send + Sync>),
+
+    #[error(transparent)]
+    Docker(#[from] docker_api::Error),
+
+    #[error(transparent)]
+    OpenSSL(#[from] openssl::error::ErrorStack),
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+/// Connection to the API, which has to be kept until it is no longer used
+pub struct Connection {
+    pub docker: Docker,
+    _bridge: Option<TlsBridge>,
+}
+
+impl Endpoint {
+    /// The configured host, otherwise `DOCKER_HOST`, otherwise the first socket that exists
+    fn host(&self) -> String {
+        if let Some(host) = self.host.clone().or_else(|| env::var("DOCKER_HOST").ok()) {
+            return host;
+        }
+
+        let user_sockets = env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .into_iter()
+            .flat_map(|runtime_dir| USER_SOCKETS.map(|socket| runtime_dir.join(socket)));
+        let socket = SYSTEM_SOCKETS
+            .iter()
+            .map(PathBuf::from)
+            .chain(user_sockets)
+            .find(|socket| socket.exists())
+            .unwrap_or_else(|| PathBuf::from(SYSTEM_SOCKETS[0]));
+        format!("unix://{}", socket.display())
+    }
+
+    /// Certificates for TLS if configured, or if enabled through the environment like for the Docker CLI
+    fn tls_cert_path(&self) -> Option<PathBuf> {
+        if self.tls_cert_path.is_some() {
+            return self.tls_cert_path.clone();
+        }
+        if self.host.is_some() || env::var("DOCKER_TLS_VERIFY").unwrap_or_default().is_empty() {
+            return None;
+        }
+        env::var_os("DOCKER_CERT_PATH")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".docker")))
+    }
+
+    pub async fn connect(&self) -> Result<Connection, EndpointError> {
+        let host = self.host();
+        let (scheme, address) = host
+            .split_once("://")
+            .ok_or_else(|| EndpointError::UnsupportedHost(host.clone()))?;
+
+        match (scheme, self.tls_cert_path()) {
+            ("unix", _) if !Path::new(address).exists() => {
+                Err(EndpointError::SocketNotFound(address.to_string()))
+            }
+            ("unix", _) => Ok(Connection {
+                docker: Docker::unix(address),
+                _bridge: None,
+            }),
+            ("tcp" | "https", Some(cert_path)) => {
+                let bridge = TlsBridge::new(address, &cert_path, self.tls_verify).await?;
+                Ok(Connection {
+                    docker: Docker::unix(&bridge.socket),
+                    _bridge: Some(bridge),
+                })
+            }
+            ("tcp" | "http", None) => Ok(Connection {
+                docker: Docker::tcp(address)?,
+                _bridge: None,
+            }),
+            _ => Err(EndpointError::UnsupportedHost(host)),
+        }
+    }
+}
+
+/// docker-api can only connect over TLS with its `tls` feature and the dependencies it pulls in,
+/// so TLS is done here with the OpenSSL we already use, for a socket only we can access
+struct TlsBridge {
+    /// Removed with the socket when dropped
+    _directory: TempDir,
+    socket: PathBuf,
+    task: JoinHandle<()>,
+}
+
+impl TlsBridge {
+    async fn new(address: &str, cert_path: &Path, verify: bool) -> Result<Self, EndpointError> {
+        let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+        builder.set_certificate_chain_file(cert_path.join("cert.pem"))?;
+        builder.set_private_key_file(cert_path.join("key.pem"), SslFiletype::PEM)?;
+        match verify {
+            true => builder.set_ca_file(cert_path.join("ca.pem"))?,
+            false => builder.set_verify(SslVerifyMode::NONE),
+        }
+        let connector = builder.build();
+
+        // Without the port and the brackets of IPv6 addresses
+        let domain = address
+            .rsplit_once(':')
+            .map_or(address, |(domain, _port)| domain)
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let address = address.to_string();
+
+        // Connect once right away, otherwise errors only show as closed connections
+        connect_tls(&connector, &address, &domain)
+            .await
+            .map_err(|error| EndpointError::Tls(address.clone(), error))?;
+
+        // A new directory with a random name, so nobody else can have access to it
+        let parent = env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(env::temp_dir);
+        let directory = tempfile::Builder::new()
+            .prefix("rust-motd-docker-")
+            .permissions(Permissions::from_mode(0o700))
+            .tempdir_in(parent)?;
+        let socket = directory.path().join("docker.sock");
+        let listener = UnixListener::bind(&socket)?;
+
+        let task = tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                let connector = connector.clone();
+                let address = address.clone();
+                let domain = domain.clone();
+                tokio::spawn(async move {
+                    if let Ok(mut server) = connect_tls(&connector, &address, &domain).await {
+                        let _ = copy_bidirectional(&mut client, &mut server).await;
+                    }
+                });
+            }
+        });
+
+        Ok(TlsBridge {
+            _directory: directory,
+            socket,
+            task,
+        })
+    }
+}
+
+impl Drop for TlsBridge {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn connect_tls(
+    connector: &SslConnector,
+    address: &str,
+    domain: &str,
+) -> Result<SslStream<TcpStream>, Box<dyn std::error::Error + Send + Sync>> {
+    let ssl = connector.configure()?.into_ssl(domain)?;
+    let mut stream = SslStream::new(ssl, TcpStream::connect(address).await?)?;
+    Pin::new(&mut stream).connect().await?;
+    Ok(stream)
+}
@@ -1,31 +1,64 @@
 use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::runtime::Handle;
 
 mod command;
 mod components;
 mod config;
 mod constants;
+mod docker;
+mod pickle;
+mod render;
+mod section;
+mod severity;
+mod systemd;
 use component::{BoxedComponent, Constraints};
 use config::get_config::get_config;
+use render::text::{ColorChoice, TextStyle};
+use render::Format;
+use severity::Severity;
 mod component;
 
 #[derive(Parser, Debug)]
 struct Args {
     config_path: Option<String>,
+
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// When to use colours in the text format
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Also write metrics in the Prometheus exposition format to this file
+    /// (for the textfile collector of node_exporter)
+    #[arg(long, value_name = "PATH")]
+    prometheus_file: Option<PathBuf>,
+
+    /// Exit with the code of a Nagios plugin for the worst problem found
+    /// (0 for OK, 1 for WARNING, 2 for CRITICAL, 3 for UNKNOWN)
+    #[arg(long)]
+    exit_code: bool,
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    match get_config(args.config_path) {
+    let severity = match get_config(args.config_path) {
         Ok(config) => {
             // Run the prepare phase for each component
             // Allow each component to specify its sizing constraints (like min width)
-            let (components, constraints): (Vec<BoxedComponent>, Vec<Option<Constraints>>) = config
-                .components
-                .into_iter()
-                .map(|component| component.prepare(&config.global))
-                .unzip();
+            let (names, components): (Vec<String>, Vec<BoxedComponent>) =
+                config.components.into_iter().unzip();
+            let (components, constraints): (Vec<BoxedComponent>, Vec<Option<Constraints>>) =
+                components
+                    .into_iter()
+                    .map(|component| component.prepare(&config.global))
+                    .unzip();
 
             // The width to use is the maximum of all the component's minimum widths
             // Right now, min width is the only constraint
@@ -35,12 +68,61 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .filter_map(|x| x.min_width)
                 .max();
 
-            // Print each component with the given width
-            for component in components {
-                component.print(&config.global, width).await;
+            // Gather the data of all components concurrently
+            // Most components block on I/O (running commands, HTTP requests),
+            // so each one runs on the blocking thread pool instead of the async workers
+            let global_config = Arc::new(config.global);
+            let handles: Vec<_> = components
+                .into_iter()
+                .map(|component| {
+                    let global_config = Arc::clone(&global_config);
+                    tokio::task::spawn_blocking(move || {
+                        Handle::current().block_on(component.get_output(&global_config))
+                    })
+                })
+                .collect();
+
+            // Collect the outputs in the order of the configuration file
+            let mut outputs = Vec::with_capacity(handles.len());
+            for (name, handle) in names.into_iter().zip(handles) {
+                outputs.push((name, handle.await?));
             }
+
+            let sections = || -> Vec<_> {
+                outputs
+                    .iter()
+                    .map(|(_, output)| output.to_section(&global_config, width))
+                    .collect()
+            };
+            match args.format {
+                Format::Text => {
+                    let text_style = TextStyle::new(args.color);
+                    print!(
+                        "{}",
+                        render::text::render(&sections(), &global_config, &text_style)
+                    );
+                }
+                Format::Json => print!("{}", render::json::render(&outputs)?),
+                Format::Prometheus => print!("{}", render::prometheus::render(&outputs)),
+                Format::Html => print!("{}", render::html::render(&sections(), &global_config)),
+                Format::Svg => print!("{}", render::svg::render(&sections(), &global_config)),
+                Format::Summary => print!("{}", render::summary::render(&outputs)),
+            }
+
+            if let Some(path) = args.prometheus_file {
+                render::prometheus::write(&outputs, &path)?;
+            }
+
+            render::summary::severity(&outputs)
+        }
+        Err(e) => {
+            println!("Config Error: {}", e);
+            Severity::Unknown
         }
-        Err(e) => println!("Config Error: {}", e),
+    };
+
+    match args.exit_code {
+        true => Ok(ExitCode::from(severity.exit_code())),
+        false => Ok(ExitCode::SUCCESS),
     }
-    Ok(())
 }
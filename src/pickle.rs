@@ -0,0 +1,444 @@
+use std::cmp::Ordering;
+use thiserror::Error;
+
+/// The subset of Python objects that are exchanged with services like fail2ban
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    /// Lists, tuples and sets
+    List(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    /// A class or function, by module and name
+    Global(String, String),
+    /// An instance of a class, by the arguments it was created with
+    Object(Vec<Value>),
+}
+
+impl Value {
+    /// Also the value of instances of subclasses of `str`, like the IP addresses of fail2ban
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            Value::Object(args) => args.first()?.as_str(),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(int) => Some(*int),
+            Value::Bool(bool) => Some(i64::from(*bool)),
+            Value::Object(args) => args.first()?.as_int(),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PickleError {
+    #[error("Unexpected end of pickle")]
+    UnexpectedEnd,
+
+    #[error("Unsupported pickle opcode 0x{0:02x}")]
+    UnsupportedOpcode(u8),
+
+    #[error("Invalid pickle: {0}")]
+    Invalid(&'static str),
+}
+
+/// Serialize a list of strings, like the commands sent to fail2ban, with protocol 2
+pub fn dumps(strings: &[&str]) -> Vec<u8> {
+    let mut pickle = vec![0x80, 2, b']', b'('];
+    for string in strings {
+        pickle.push(b'X');
+        pickle.extend((string.len() as u32).to_le_bytes());
+        pickle.extend(string.as_bytes());
+    }
+    pickle.extend([b'e', b'.']);
+    pickle
+}
+
+/// Stack machine of the pickle format, up to protocol 5
+///
+/// Memoized objects are copied instead of shared,
+/// which only matters for objects that are modified after being referenced again
+struct Unpickler<'a> {
+    input: &'a [u8],
+    stack: Vec<Value>,
+    /// Positions in the stack where `MARK` was pushed
+    marks: Vec<usize>,
+    memo: Vec<Value>,
+}
+
+impl<'a> Unpickler<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8], PickleError> {
+        if self.input.len() < len {
+            return Err(PickleError::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn read_uint(&mut self, len: usize) -> Result<usize, PickleError> {
+        let bytes = self.read(len)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, byte| (value << 8) | *byte as usize))
+    }
+
+    fn read_line(&mut self) -> Result<String, PickleError> {
+        let len = self
+            .input
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or(PickleError::UnexpectedEnd)?;
+        let line = String::from_utf8_lossy(self.read(len)?).into_owned();
+        self.read(1)?;
+        Ok(line)
+    }
+
+    fn read_string(&mut self, len: usize) -> Result<Value, PickleError> {
+        let bytes = self.read(len)?;
+        Ok(Value::String(String::from_utf8_lossy(bytes).into_owned()))
+    }
+
+    /// Little-endian two's complement integer as in `LONG1`
+    fn read_long(&mut self, len: usize) -> Result<Value, PickleError> {
+        let bytes = self.read(len)?;
+        if len > 8 {
+            return Err(PickleError::Invalid("integer too large"));
+        }
+        let negative = bytes.last().is_some_and(|byte| byte & 0x80 != 0);
+        let mut buffer = [if negative { 0xff } else { 0 }; 8];
+        buffer[..len].copy_from_slice(bytes);
+        Ok(Value::Int(i64::from_le_bytes(buffer)))
+    }
+
+    fn pop(&mut self) -> Result<Value, PickleError> {
+        match self.marks.last() {
+            Some(mark) if *mark >= self.stack.len() => Err(PickleError::Invalid("pop past mark")),
+            _ => self.stack.pop().ok_or(PickleError::Invalid("empty stack")),
+        }
+    }
+
+    fn pop_mark(&mut self) -> Result<Vec<Value>, PickleError> {
+        let mark = self
+            .marks
+            .pop()
+            .ok_or(PickleError::Invalid("missing mark"))?;
+        Ok(self.stack.split_off(mark))
+    }
+
+    fn top(&mut self) -> Result<&mut Value, PickleError> {
+        self.stack
+            .last_mut()
+            .ok_or(PickleError::Invalid("empty stack"))
+    }
+
+    fn extend(&mut self, items: Vec<Value>) -> Result<(), PickleError> {
+        match self.top()? {
+            Value::List(list) => {
+                list.extend(items);
+                Ok(())
+            }
+            _ => Err(PickleError::Invalid("append to non-list")),
+        }
+    }
+
+    fn set_items(&mut self, items: Vec<Value>) -> Result<(), PickleError> {
+        if !items.len().is_multiple_of(2) {
+            return Err(PickleError::Invalid("odd number of dict items"));
+        }
+        let mut items = items.into_iter();
+        match self.top()? {
+            Value::Dict(dict) => {
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    dict.push((key, value));
+                }
+                Ok(())
+            }
+            _ => Err(PickleError::Invalid("set item of non-dict")),
+        }
+    }
+
+    fn get(&mut self, index: usize) -> Result<(), PickleError> {
+        let value = self
+            .memo
+            .get(index)
+            .cloned()
+            .ok_or(PickleError::Invalid("missing memo"))?;
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Python numbers memoized objects in order, so a larger index can only come from a
+    /// corrupt pickle and would otherwise grow the memo to that size
+    fn put(&mut self, index: usize) -> Result<(), PickleError> {
+        let value = self.top()?.clone();
+        match index.cmp(&self.memo.len()) {
+            Ordering::Less => self.memo[index] = value,
+            Ordering::Equal => self.memo.push(value),
+            Ordering::Greater => return Err(PickleError::Invalid("memo index out of order")),
+        }
+        Ok(())
+    }
+
+    fn load(mut self) -> Result<Value, PickleError> {
+        loop {
+            let opcode = self.read(1)?[0];
+            let value = match opcode {
+                // PROTO
+                0x80 => {
+                    self.read(1)?;
+                    continue;
+                }
+                // FRAME
+                0x95 => {
+                    self.read(8)?;
+                    continue;
+                }
+                // STOP
+                b'.' => return self.pop(),
+                b'N' => Value::None,
+                0x88 => Value::Bool(true),
+                0x89 => Value::Bool(false),
+                // BININT1, BININT2, BININT
+                b'K' => Value::Int(self.read_uint(1)? as i64),
+                b'M' => Value::Int(self.read_uint(2)? as i64),
+                b'J' => Value::Int(self.read_uint(4)? as u32 as i32 as i64),
+                // LONG1, LONG4
+                0x8a => {
+                    let len = self.read_uint(1)?;
+                    self.read_long(len)?
+                }
+                0x8b => {
+                    let len = self.read_uint(4)?;
+                    self.read_long(len)?
+                }
+                // BINFLOAT
+                b'G' => {
+                    let bytes = self.read(8)?.try_into().unwrap_or_default();
+                    Value::Float(f64::from_be_bytes(bytes))
+                }
+                // SHORT_BINUNICODE, BINUNICODE, BINUNICODE8
+                0x8c => {
+                    let len = self.read_uint(1)?;
+                    self.read_string(len)?
+                }
+                b'X' => {
+                    let len = self.read_uint(4)?;
+                    self.read_string(len)?
+                }
+                0x8d => {
+                    let len = self.read_uint(8)?;
+                    self.read_string(len)?
+                }
+                // SHORT_BINBYTES, BINBYTES, BINBYTES8
+                b'C' | b'B' | 0x8e => {
+                    let len = match opcode {
+                        b'C' => self.read_uint(1)?,
+                        b'B' => self.read_uint(4)?,
+                        _ => self.read_uint(8)?,
+                    };
+                    Value::Bytes(self.read(len)?.to_vec())
+                }
+                // EMPTY_TUPLE, EMPTY_LIST, EMPTY_SET
+                b')' | b']' | 0x8f => Value::List(vec![]),
+                b'}' => Value::Dict(vec![]),
+                // TUPLE1, TUPLE2, TUPLE3
+                0x85..=0x87 => {
+                    let len = (opcode - 0x84) as usize;
+                    let mut items = (0..len)
+                        .map(|_| self.pop())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    items.reverse();
+                    Value::List(items)
+                }
+                b'(' => {
+                    self.marks.push(self.stack.len());
+                    continue;
+                }
+                // TUPLE, LIST, FROZENSET
+                b't' | b'l' | 0x91 => Value::List(self.pop_mark()?),
+                b'd' => {
+                    let items = self.pop_mark()?;
+                    self.stack.push(Value::Dict(vec![]));
+                    self.set_items(items)?;
+                    continue;
+                }
+                b'a' => {
+                    let item = self.pop()?;
+                    self.extend(vec![item])?;
+                    continue;
+                }
+                // APPENDS, ADDITEMS
+                b'e' | 0x90 => {
+                    let items = self.pop_mark()?;
+                    self.extend(items)?;
+                    continue;
+                }
+                b's' => {
+                    let value = self.pop()?;
+                    let key = self.pop()?;
+                    self.set_items(vec![key, value])?;
+                    continue;
+                }
+                b'u' => {
+                    let items = self.pop_mark()?;
+                    self.set_items(items)?;
+                    continue;
+                }
+                // MEMOIZE, BINPUT, LONG_BINPUT
+                0x94 => {
+                    self.put(self.memo.len())?;
+                    continue;
+                }
+                b'q' => {
+                    let index = self.read_uint(1)?;
+                    self.put(index)?;
+                    continue;
+                }
+                b'r' => {
+                    let index = self.read_uint(4)?;
+                    self.put(index)?;
+                    continue;
+                }
+                // BINGET, LONG_BINGET
+                b'h' => {
+                    let index = self.read_uint(1)?;
+                    self.get(index)?;
+                    continue;
+                }
+                b'j' => {
+                    let index = self.read_uint(4)?;
+                    self.get(index)?;
+                    continue;
+                }
+                // GLOBAL, STACK_GLOBAL
+                b'c' => Value::Global(self.read_line()?, self.read_line()?),
+                0x93 => {
+                    let name = self.pop()?;
+                    let module = self.pop()?;
+                    match (module.as_str(), name.as_str()) {
+                        (Some(module), Some(name)) => {
+                            Value::Global(module.to_string(), name.to_string())
+                        }
+                        _ => return Err(PickleError::Invalid("global without name")),
+                    }
+                }
+                // REDUCE, NEWOBJ
+                b'R' | 0x81 => {
+                    let args = self.pop()?;
+                    self.pop()?;
+                    Value::Object(args.as_list().unwrap_or_default().to_vec())
+                }
+                // NEWOBJ_EX
+                0x92 => {
+                    self.pop()?;
+                    let args = self.pop()?;
+                    self.pop()?;
+                    Value::Object(args.as_list().unwrap_or_default().to_vec())
+                }
+                // BUILD, the state of an object is not needed
+                b'b' => {
+                    self.pop()?;
+                    continue;
+                }
+                // POP, POP_MARK, DUP
+                b'0' => {
+                    self.pop()?;
+                    continue;
+                }
+                b'1' => {
+                    self.pop_mark()?;
+                    continue;
+                }
+                b'2' => self.top()?.clone(),
+                _ => return Err(PickleError::UnsupportedOpcode(opcode)),
+            };
+            self.stack.push(value);
+        }
+    }
+}
+
+/// Deserialize a pickle, without executing any code
+pub fn loads(input: &[u8]) -> Result<Value, PickleError> {
+    Unpickler {
+        input,
+        stack: vec![],
+        marks: vec![],
+        memo: vec![],
+    }
+    .load()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumped_commands_are_loaded_back() {
+        let command = ["get", "sshd", "banip", "--with-time"];
+        assert_eq!(
+            loads(&dumps(&command)).unwrap(),
+            Value::List(command.map(|word| Value::String(word.to_string())).to_vec())
+        );
+    }
+
+    #[test]
+    fn truncated_pickles_are_errors() {
+        let pickle = dumps(&["status", "sshd"]);
+        for len in 0..pickle.len() {
+            assert!(loads(&pickle[..len]).is_err(), "prefix of {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        for garbage in [
+            &b"garbage"[..],
+            b"\x80\x04\xff.",
+            // Pop and append without anything on the stack
+            b"0.",
+            b"]a.",
+            // Get before put
+            b"h\x00.",
+            // Dict items without a mark
+            b"}u.",
+            // Global without a name
+            b"N\x93.",
+        ] {
+            assert!(loads(garbage).is_err(), "{:?}", garbage);
+        }
+    }
+
+    #[test]
+    fn memo_index_is_bounded() {
+        assert!(matches!(
+            loads(b"\x80\x02]r\xff\xff\xff\xff."),
+            Err(PickleError::Invalid(_))
+        ));
+        // An index that is already used replaces the memoized value
+        assert_eq!(
+            loads(b"\x80\x02K\x01q\x00K\x02q\x00\x86h\x00\x86.").unwrap(),
+            Value::List(vec![
+                Value::List(vec![Value::Int(1), Value::Int(2)]),
+                Value::Int(2)
+            ])
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use crate::config::global_config::GlobalConfig;
+use crate::render::styled::{escape_xml, lines, BACKGROUND, FOREGROUND};
+use crate::section::Section;
+
+/// Render the sections as a standalone HTML page
+pub fn render(sections: &[Section], global_config: &GlobalConfig) -> String {
+    let body = lines(sections, global_config)
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|span| match &span.color {
+                    Some(color) => format!(
+                        "<span style=\"color: {}\">{}</span>",
+                        color,
+                        escape_xml(&span.text)
+                    ),
+                    None => escape_xml(&span.text),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "<head>\n",
+            "<meta charset=\"utf-8\">\n",
+            "<title>rust-motd</title>\n",
+            "<style>\n",
+            "body {{ background: {background}; color: {foreground}; }}\n",
+            "pre {{ font-family: monospace; }}\n",
+            "</style>\n",
+            "</head>\n",
+            "<body>\n",
+            "<pre>\n",
+            "{body}\n",
+            "</pre>\n",
+            "</body>\n",
+            "</html>\n",
+        ),
+        background = BACKGROUND,
+        foreground = FOREGROUND,
+        body = body,
+    )
+}
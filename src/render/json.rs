@@ -0,0 +1,33 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::time::Duration;
+
+use crate::component::BoxedOutput;
+
+/// The outputs of all components, keyed by the name of their section in the config file
+/// Serialized as a map that keeps the order of the config file
+struct Document<'a>(&'a [(String, BoxedOutput)]);
+
+impl Serialize for Document<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, output) in self.0 {
+            let value = output.to_json().map_err(serde::ser::Error::custom)?;
+            map.serialize_entry(name, &value)?;
+        }
+        map.end()
+    }
+}
+
+/// Render the outputs of all components as one JSON document
+pub fn render(outputs: &[(String, BoxedOutput)]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Document(outputs)).map(|json| json + "\n")
+}
+
+/// Serialize a duration as a whole number of seconds
+/// For use with `#[serde(serialize_with = "...")]`
+pub fn serialize_seconds<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
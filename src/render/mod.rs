@@ -0,0 +1,26 @@
+use clap::ValueEnum;
+
+pub mod html;
+pub mod json;
+pub mod prometheus;
+pub mod styled;
+pub mod summary;
+pub mod svg;
+pub mod text;
+
+/// The formats the motd can be rendered in
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    /// Text for the terminal
+    Text,
+    /// One JSON document with the data of all components
+    Json,
+    /// Metrics in the Prometheus exposition format
+    Prometheus,
+    /// A standalone HTML page
+    Html,
+    /// A standalone SVG image
+    Svg,
+    /// One line summarizing the problems found by all components
+    Summary,
+}
@@ -0,0 +1,108 @@
+use itertools::Itertools;
+use std::fs;
+use std::path::Path;
+
+use crate::component::BoxedOutput;
+
+/// One sample of a gauge in the Prometheus exposition format
+pub struct Metric {
+    name: &'static str,
+    help: &'static str,
+    labels: Vec<(&'static str, String)>,
+    value: f64,
+}
+
+impl Metric {
+    pub fn new<V: Into<f64>>(name: &'static str, help: &'static str, value: V) -> Metric {
+        Metric {
+            name,
+            help,
+            labels: vec![],
+            value: value.into(),
+        }
+    }
+
+    pub fn label<S: Into<String>>(mut self, name: &'static str, value: S) -> Metric {
+        self.labels.push((name, value.into()));
+        self
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        match value.is_sign_positive() {
+            true => "+Inf".to_string(),
+            false => "-Inf".to_string(),
+        }
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_sample(metric: &Metric) -> String {
+    let labels = match metric.labels.is_empty() {
+        true => String::new(),
+        false => format!(
+            "{{{}}}",
+            metric
+                .labels
+                .iter()
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+                .join(",")
+        ),
+    };
+    format!("{}{} {}\n", metric.name, labels, format_value(metric.value))
+}
+
+/// Render the metrics of all components in the Prometheus exposition format
+/// Samples of the same metric are grouped together under one `HELP` and `TYPE` line
+/// A component that fails has no metrics of its own, so whether each one succeeded is a metric too
+pub fn render(outputs: &[(String, BoxedOutput)]) -> String {
+    let metrics: Vec<Metric> = outputs
+        .iter()
+        .map(|(name, output)| {
+            Metric::new(
+                "motd_component_success",
+                "Whether the component gathered its data",
+                !output.is_error(),
+            )
+            .label("component", name)
+        })
+        .chain(outputs.iter().flat_map(|(_, output)| output.metrics()))
+        .collect();
+
+    let mut out = String::new();
+    for name in metrics.iter().map(|metric| metric.name).unique() {
+        let mut samples = metrics
+            .iter()
+            .filter(|metric| metric.name == name)
+            .peekable();
+        if let Some(first) = samples.peek() {
+            out.push_str(&format!("# HELP {} {}\n", name, first.help));
+            out.push_str(&format!("# TYPE {} gauge\n", name));
+        }
+        for metric in samples {
+            out.push_str(&format_sample(metric));
+        }
+    }
+    out
+}
+
+/// Write the metrics to a file for the textfile collector of node_exporter
+/// The file is written next to its destination and then renamed,
+/// so the collector never reads a partially written file
+pub fn write(outputs: &[(String, BoxedOutput)], path: &Path) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    fs::write(&temp_path, render(outputs))?;
+    fs::rename(&temp_path, path)
+}
@@ -0,0 +1,210 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::config::global_config::GlobalConfig;
+use crate::section::{Bar, Color, Element, Section};
+
+/// Colour used for text without a colour of its own
+pub const FOREGROUND: &str = "#cccccc";
+pub const BACKGROUND: &str = "#1e1e1e";
+
+/// A piece of text with a CSS colour
+/// This is the common ground of the renderers that do not understand terminal escape codes
+pub struct Span {
+    pub text: String,
+    pub color: Option<String>,
+}
+
+pub type StyledLine = Vec<Span>;
+
+/// The 16 terminal colours, in the order of their escape codes
+const PALETTE: [&str; 16] = [
+    "#000000", "#cd3131", "#0dbc79", "#e5e510", "#2472c8", "#bc3fbc", "#11a8cd", "#e5e5e5",
+    "#666666", "#f14c4c", "#23d18b", "#f5f543", "#3b8eea", "#d670d6", "#29b8db", "#ffffff",
+];
+
+pub fn css_color(color: Color) -> String {
+    let index = match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::White => 7,
+        Color::LightBlack => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::LightWhite => 15,
+    };
+    PALETTE[index].to_string()
+}
+
+/// CSS colour of an entry of the 256 colour palette of terminals
+fn css_color_256(index: u8) -> String {
+    match index {
+        0..=15 => PALETTE[index as usize].to_string(),
+        16..=231 => {
+            let level = |x: u8| match x {
+                0 => 0,
+                x => 55 + 40 * x,
+            };
+            let index = index - 16;
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                level(index / 36),
+                level((index / 6) % 6),
+                level(index % 6)
+            )
+        }
+        232..=255 => {
+            let gray = 8 + 10 * (index - 232);
+            format!("#{:02x}{:02x}{:02x}", gray, gray, gray)
+        }
+    }
+}
+
+/// Apply the parameters of a "select graphic rendition" escape code to the current colour
+/// Only the foreground colour is kept, everything else (bold, background, ...) is ignored
+fn apply_sgr(params: &str, color: &mut Option<String>) {
+    let mut params = params
+        .split(';')
+        .map(|param| param.parse::<u8>().unwrap_or(0));
+    while let Some(param) = params.next() {
+        match param {
+            0 | 39 => *color = None,
+            30..=37 => *color = Some(PALETTE[(param - 30) as usize].to_string()),
+            90..=97 => *color = Some(PALETTE[(param - 90 + 8) as usize].to_string()),
+            38 => match params.next() {
+                Some(5) => *color = params.next().map(css_color_256),
+                Some(2) => {
+                    let rgb: Vec<u8> = params.by_ref().take(3).collect();
+                    if let [r, g, b] = rgb[..] {
+                        *color = Some(format!("#{:02x}{:02x}{:02x}", r, g, b));
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Split text containing terminal escape codes (for example from a banner command)
+/// into spans with the colours the escape codes select
+fn parse_escape_codes(text: &str, color: &mut Option<String>) -> Vec<Span> {
+    lazy_static! {
+        static ref ESCAPE_REGEX: Regex = Regex::new(r"\x1b\[([0-9;?]*)([A-Za-z])").unwrap();
+    }
+
+    let mut spans = vec![];
+    let mut start = 0;
+    for captures in ESCAPE_REGEX.captures_iter(text) {
+        let escape = captures.get(0).unwrap();
+        if escape.start() > start {
+            spans.push(Span {
+                text: text[start..escape.start()].to_string(),
+                color: color.clone(),
+            });
+        }
+        if &captures[2] == "m" {
+            apply_sgr(&captures[1], color);
+        }
+        start = escape.end();
+    }
+    if start < text.len() {
+        spans.push(Span {
+            text: text[start..].to_string(),
+            color: color.clone(),
+        });
+    }
+    spans
+}
+
+fn bar_spans(bar: &Bar, global_config: &GlobalConfig) -> Vec<Span> {
+    let without_ends_width = bar
+        .width
+        .saturating_sub(global_config.progress_prefix.len() + global_config.progress_suffix.len());
+
+    let bar_full = ((without_ends_width as f64) * bar.full_ratio) as usize;
+    let bar_empty = without_ends_width.saturating_sub(bar_full);
+
+    vec![
+        Span {
+            text: global_config.progress_prefix.to_string(),
+            color: None,
+        },
+        Span {
+            text: global_config
+                .progress_full_character
+                .to_string()
+                .repeat(bar_full),
+            color: Some(css_color(bar.full_color)),
+        },
+        Span {
+            text: global_config
+                .progress_empty_character
+                .to_string()
+                .repeat(bar_empty),
+            color: Some(css_color(Color::LightBlack)),
+        },
+        Span {
+            text: global_config.progress_suffix.to_string(),
+            color: None,
+        },
+    ]
+}
+
+/// Lay out the sections as lines of coloured spans, separated by blank lines
+pub fn lines(sections: &[Section], global_config: &GlobalConfig) -> Vec<StyledLine> {
+    let mut lines = vec![];
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            lines.push(vec![]);
+        }
+        if let Some(title) = &section.title {
+            lines.push(vec![Span {
+                text: format!("{}:", title),
+                color: None,
+            }]);
+        }
+        for line in &section.lines {
+            // Colours selected by escape codes carry over to the following text of the line,
+            // just like in a terminal
+            let mut escape_color = None;
+            let mut styled_line = vec![];
+            for element in line {
+                match element {
+                    Element::Text { text, color: None } => {
+                        styled_line.extend(parse_escape_codes(text, &mut escape_color))
+                    }
+                    Element::Text {
+                        text,
+                        color: Some(color),
+                    } => styled_line.extend(parse_escape_codes(text, &mut None).into_iter().map(
+                        |span| Span {
+                            color: span.color.or_else(|| Some(css_color(*color))),
+                            ..span
+                        },
+                    )),
+                    Element::Bar(bar) => styled_line.extend(bar_spans(bar, global_config)),
+                }
+            }
+            lines.push(styled_line);
+        }
+    }
+    lines
+}
+
+/// Escape text for use in HTML and SVG
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
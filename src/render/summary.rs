@@ -0,0 +1,35 @@
+use itertools::Itertools;
+
+use crate::component::BoxedOutput;
+use crate::severity::Severity;
+
+/// The worst severity of all components
+pub fn severity(outputs: &[(String, BoxedOutput)]) -> Severity {
+    outputs
+        .iter()
+        .map(|(_, output)| output.severity())
+        .max()
+        .unwrap_or(Severity::Ok)
+}
+
+/// Render the problems of all components as one line, as expected from a Nagios plugin
+/// For example `CRITICAL - filesystems: root (/) is 97% full; docker: nextcloud is dead`
+pub fn render(outputs: &[(String, BoxedOutput)]) -> String {
+    let problems: Vec<String> = outputs
+        .iter()
+        .flat_map(|(name, output)| {
+            output
+                .problems()
+                .into_iter()
+                .map(move |problem| (name, problem))
+        })
+        // Worst problems first, in case the line gets cut off
+        .sorted_by_key(|(_, problem)| std::cmp::Reverse(problem.severity))
+        .map(|(name, problem)| format!("{}: {}", name, problem.message))
+        .collect();
+
+    match problems.is_empty() {
+        true => format!("{} - no problems found\n", Severity::Ok.label()),
+        false => format!("{} - {}\n", severity(outputs).label(), problems.join("; ")),
+    }
+}
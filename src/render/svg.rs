@@ -0,0 +1,65 @@
+use crate::config::global_config::GlobalConfig;
+use crate::render::styled::{escape_xml, lines, BACKGROUND, FOREGROUND};
+use crate::section::Section;
+
+const FONT_SIZE: f64 = 14.0;
+/// Width of one character of a typical monospace font at `FONT_SIZE`
+const CHAR_WIDTH: f64 = 8.4;
+const LINE_HEIGHT: f64 = 18.0;
+const PADDING: f64 = 10.0;
+
+/// Render the sections as a standalone SVG image of a terminal
+pub fn render(sections: &[Section], global_config: &GlobalConfig) -> String {
+    let lines = lines(sections, global_config);
+
+    let columns = lines
+        .iter()
+        .map(|line| line.iter().map(|span| span.text.chars().count()).sum())
+        .max()
+        .unwrap_or(0);
+    let width = 2.0 * PADDING + columns as f64 * CHAR_WIDTH;
+    let height = 2.0 * PADDING + lines.len() as f64 * LINE_HEIGHT;
+
+    let text = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let spans = line
+                .iter()
+                .map(|span| match &span.color {
+                    Some(color) => format!(
+                        "<tspan fill=\"{}\">{}</tspan>",
+                        color,
+                        escape_xml(&span.text)
+                    ),
+                    None => escape_xml(&span.text),
+                })
+                .collect::<String>();
+            format!(
+                "<text x=\"{}\" y=\"{}\">{}</text>\n",
+                PADDING,
+                PADDING + (i + 1) as f64 * LINE_HEIGHT - (LINE_HEIGHT - FONT_SIZE),
+                spans
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" ",
+            "viewBox=\"0 0 {width} {height}\">\n",
+            "<rect width=\"100%\" height=\"100%\" fill=\"{background}\"/>\n",
+            "<g font-family=\"monospace\" font-size=\"{font_size}\" fill=\"{foreground}\" ",
+            "xml:space=\"preserve\">\n",
+            "{text}",
+            "</g>\n",
+            "</svg>\n",
+        ),
+        width = width,
+        height = height,
+        background = BACKGROUND,
+        foreground = FOREGROUND,
+        font_size = FONT_SIZE,
+        text = text,
+    )
+}
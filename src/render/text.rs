@@ -0,0 +1,164 @@
+use clap::ValueEnum;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::borrow::Cow;
+use std::env;
+use termion::{color, style};
+
+use crate::config::global_config::GlobalConfig;
+use crate::section::{Bar, Color, Element, Section};
+
+/// When to print colours
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ColorChoice {
+    /// Only if stdout is a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether the text is printed with colours
+pub struct TextStyle {
+    pub colored: bool,
+}
+
+impl TextStyle {
+    /// Follows https://no-color.org for `ColorChoice::Auto`:
+    /// `NO_COLOR` disables colours if it is set and not empty
+    pub fn new(color: ColorChoice) -> TextStyle {
+        let colored = match color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && termion::is_tty(&std::io::stdout())
+            }
+        };
+        TextStyle { colored }
+    }
+
+    fn fg(&self, color: Color) -> String {
+        match self.colored {
+            true => fg(color),
+            false => String::new(),
+        }
+    }
+
+    fn reset(&self) -> String {
+        match self.colored {
+            true => style::Reset.to_string(),
+            false => String::new(),
+        }
+    }
+
+    /// Remove the escape codes that come with text from other programs
+    /// (for example a banner command or the weather service) if colours are disabled
+    fn strip<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        lazy_static! {
+            static ref ESCAPE_REGEX: Regex = Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").unwrap();
+        }
+
+        match self.colored {
+            true => Cow::Borrowed(text),
+            false => ESCAPE_REGEX.replace_all(text, ""),
+        }
+    }
+}
+
+fn fg(color: Color) -> String {
+    match color {
+        Color::Black => color::Fg(color::Black).to_string(),
+        Color::Red => color::Fg(color::Red).to_string(),
+        Color::Green => color::Fg(color::Green).to_string(),
+        Color::Yellow => color::Fg(color::Yellow).to_string(),
+        Color::Blue => color::Fg(color::Blue).to_string(),
+        Color::Magenta => color::Fg(color::Magenta).to_string(),
+        Color::Cyan => color::Fg(color::Cyan).to_string(),
+        Color::White => color::Fg(color::White).to_string(),
+        Color::LightBlack => color::Fg(color::LightBlack).to_string(),
+        Color::LightRed => color::Fg(color::LightRed).to_string(),
+        Color::LightGreen => color::Fg(color::LightGreen).to_string(),
+        Color::LightYellow => color::Fg(color::LightYellow).to_string(),
+        Color::LightBlue => color::Fg(color::LightBlue).to_string(),
+        Color::LightMagenta => color::Fg(color::LightMagenta).to_string(),
+        Color::LightCyan => color::Fg(color::LightCyan).to_string(),
+        Color::LightWhite => color::Fg(color::LightWhite).to_string(),
+    }
+}
+
+fn render_bar(bar: &Bar, global_config: &GlobalConfig, text_style: &TextStyle) -> String {
+    let without_ends_width = bar
+        .width
+        .saturating_sub(global_config.progress_prefix.len() + global_config.progress_suffix.len());
+
+    let bar_full = ((without_ends_width as f64) * bar.full_ratio) as usize;
+    let bar_empty = without_ends_width.saturating_sub(bar_full);
+
+    [
+        global_config.progress_prefix.to_string(),
+        text_style.fg(bar.full_color),
+        global_config
+            .progress_full_character
+            .to_string()
+            .repeat(bar_full),
+        text_style.fg(Color::LightBlack),
+        global_config
+            .progress_empty_character
+            .to_string()
+            .repeat(bar_empty),
+        text_style.reset(),
+        global_config.progress_suffix.to_string(),
+    ]
+    .join("")
+}
+
+fn render_element(
+    element: &Element,
+    global_config: &GlobalConfig,
+    text_style: &TextStyle,
+) -> String {
+    match element {
+        Element::Text { text, color: None } => text_style.strip(text).to_string(),
+        Element::Text {
+            text,
+            color: Some(color),
+        } => format!(
+            "{}{}{}",
+            text_style.fg(*color),
+            text_style.strip(text),
+            text_style.reset()
+        ),
+        Element::Bar(bar) => render_bar(bar, global_config, text_style),
+    }
+}
+
+fn render_section(
+    section: &Section,
+    global_config: &GlobalConfig,
+    text_style: &TextStyle,
+) -> String {
+    let mut out = String::new();
+    if let Some(title) = &section.title {
+        out.push_str(&format!("{}:\n", title));
+    }
+    for line in &section.lines {
+        for element in line {
+            out.push_str(&render_element(element, global_config, text_style));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Render the sections as text for the terminal, separated by blank lines
+pub fn render(
+    sections: &[Section],
+    global_config: &GlobalConfig,
+    text_style: &TextStyle,
+) -> String {
+    sections
+        .iter()
+        .map(|section| render_section(section, global_config, text_style))
+        .join("\n")
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+/// Colours available to components
+/// Renderers map these onto their own representation (for example terminal escape codes)
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    LightBlack,
+    LightRed,
+    LightGreen,
+    LightYellow,
+    LightBlue,
+    LightMagenta,
+    LightCyan,
+    LightWhite,
+}
+
+/// A progress bar, drawn in the style of the global configuration
+#[derive(Debug)]
+pub struct Bar {
+    /// Width of the whole bar, including the prefix and suffix
+    pub width: usize,
+    pub full_ratio: f64,
+    pub full_color: Color,
+}
+
+/// One piece of a line
+#[derive(Debug)]
+pub enum Element {
+    Text { text: String, color: Option<Color> },
+    Bar(Bar),
+}
+
+impl Element {
+    pub fn plain<S: Into<String>>(text: S) -> Element {
+        Element::Text {
+            text: text.into(),
+            color: None,
+        }
+    }
+
+    pub fn colored<S: Into<String>>(text: S, color: Color) -> Element {
+        Element::Text {
+            text: text.into(),
+            color: Some(color),
+        }
+    }
+
+    pub fn bar(width: usize, full_ratio: f64, full_color: Color) -> Element {
+        Element::Bar(Bar {
+            width,
+            full_ratio,
+            full_color,
+        })
+    }
+}
+
+pub type Line = Vec<Element>;
+
+/// The part of the motd belonging to one component
+/// This is what components produce from their collected data,
+/// and what the renderers turn into the final output
+#[derive(Debug, Default)]
+pub struct Section {
+    pub title: Option<String>,
+    pub lines: Vec<Line>,
+}
+
+impl Section {
+    pub fn new(title: Option<&str>) -> Section {
+        Section {
+            title: title.map(String::from),
+            lines: vec![],
+        }
+    }
+
+    pub fn push(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+}
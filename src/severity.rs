@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::section::Color;
+
+/// How bad the state reported by a component is
+/// Ordered from best to worst, so the severity of the whole motd is the maximum
+/// (unknown is better than warning as in `max_state_alt` of the Nagios plugins)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Ok,
+    Unknown,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    /// Exit code following the Nagios plugin guidelines
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Severity::Ok => 0,
+            Severity::Warning => 1,
+            Severity::Critical => 2,
+            Severity::Unknown => 3,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Ok => "OK",
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+            Severity::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// Colour of bars showing a quantity with this severity
+    pub fn bar_color(self) -> Color {
+        match self {
+            Severity::Ok => Color::Green,
+            Severity::Warning | Severity::Unknown => Color::Yellow,
+            Severity::Critical => Color::Red,
+        }
+    }
+}
+
+/// Something a component found that is worth alerting on
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new<S: Into<String>>(severity: Severity, message: S) -> Problem {
+        Problem {
+            severity,
+            message: message.into(),
+        }
+    }
+}
+
+const DEFAULT_WARNING_THRESHOLD: usize = 75;
+const DEFAULT_CRITICAL_THRESHOLD: usize = 95;
+
+/// Percentages of usage (for example of a filesystem) above which the usage is a problem
+/// Thresholds that are not set fall back to those of the component, and then to the defaults
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct UsageThresholds {
+    warning: Option<usize>,
+    critical: Option<usize>,
+}
+
+impl UsageThresholds {
+    pub const fn new(warning: usize, critical: usize) -> UsageThresholds {
+        UsageThresholds {
+            warning: Some(warning),
+            critical: Some(critical),
+        }
+    }
+
+    /// Fill the thresholds that are not set from `defaults`
+    pub fn or(self, defaults: UsageThresholds) -> UsageThresholds {
+        UsageThresholds {
+            warning: self.warning.or(defaults.warning),
+            critical: self.critical.or(defaults.critical),
+        }
+    }
+
+    /// Severity of a used / total ratio
+    pub fn severity(&self, used_ratio: f64) -> Severity {
+        let percent = (used_ratio * 100.0) as usize;
+        if percent > self.critical.unwrap_or(DEFAULT_CRITICAL_THRESHOLD) {
+            Severity::Critical
+        } else if percent > self.warning.unwrap_or(DEFAULT_WARNING_THRESHOLD) {
+            Severity::Warning
+        } else {
+            Severity::Ok
+        }
+    }
+}
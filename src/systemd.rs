@@ -0,0 +1,102 @@
+use chrono::{DateTime, Local, TimeZone};
+use serde::Deserialize;
+use zbus::proxy;
+use zbus::zvariant::{OwnedObjectPath, Type};
+use zbus::Connection;
+
+/// Bus name of systemd, and the interfaces of units whose properties are read
+pub const BUS_NAME: &str = "org.freedesktop.systemd1";
+pub const UNIT_INTERFACE: &str = "org.freedesktop.systemd1.Unit";
+pub const SERVICE_INTERFACE: &str = "org.freedesktop.systemd1.Service";
+
+/// Connection to the system instance of systemd, or to the instance of the user
+/// The user instance is found through `DBUS_SESSION_BUS_ADDRESS` if it is set
+/// and `$XDG_RUNTIME_DIR/bus` otherwise, so this also works from cron
+pub async fn connect(user: bool) -> zbus::Result<Connection> {
+    match user {
+        true => Connection::session().await,
+        false => Connection::system().await,
+    }
+}
+
+/// Name of the bus `connect` uses, for error messages
+pub fn bus_name(user: bool) -> &'static str {
+    match user {
+        true => "session",
+        false => "system",
+    }
+}
+
+/// Time in microseconds since the epoch as used by systemd, where 0 means never
+pub fn timestamp(usec: u64) -> Option<DateTime<Local>> {
+    match usec {
+        0 | u64::MAX => None,
+        usec => Local
+            .timestamp_opt(
+                (usec / 1_000_000) as i64,
+                ((usec % 1_000_000) * 1000) as u32,
+            )
+            .single(),
+    }
+}
+
+/// A unit as listed by the manager
+///
+/// All fields are needed to deserialize the D-Bus structure, even when not read
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Type)]
+pub struct UnitStatus {
+    pub name: String,
+    pub description: String,
+    pub load_state: String,
+    pub active_state: String,
+    pub sub_state: String,
+    pub followed: String,
+    pub path: OwnedObjectPath,
+    pub job_id: u32,
+    pub job_type: String,
+    pub job_path: OwnedObjectPath,
+}
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+pub trait Manager {
+    /// Units with the given names in the same order, including those that are not loaded
+    fn list_units_by_names(&self, names: &[&str]) -> zbus::Result<Vec<UnitStatus>>;
+
+    /// Loaded units in one of the given states (all if empty) matching one of the patterns
+    fn list_units_by_patterns(
+        &self,
+        states: &[&str],
+        patterns: &[&str],
+    ) -> zbus::Result<Vec<UnitStatus>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Service",
+    default_service = "org.freedesktop.systemd1"
+)]
+pub trait Service {
+    /// How the last run ended, for example `success`, `exit-code` or `timeout`
+    #[zbus(property)]
+    fn result(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Timer",
+    default_service = "org.freedesktop.systemd1"
+)]
+pub trait Timer {
+    /// The unit that is activated when the timer elapses
+    #[zbus(property)]
+    fn unit(&self) -> zbus::Result<String>;
+
+    #[zbus(property, name = "LastTriggerUSec")]
+    fn last_trigger_usec(&self) -> zbus::Result<u64>;
+
+    #[zbus(property, name = "NextElapseUSecRealtime")]
+    fn next_elapse_usec_realtime(&self) -> zbus::Result<u64>;
+}